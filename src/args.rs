@@ -0,0 +1,733 @@
+/**
+* Command line parsing.
+*
+* This is a small hand-rolled parser in the spirit of getopt_long: long options may be given as
+* `--name value` or `--name=value`, short options may be grouped (`-ab`) and may carry their value
+* directly (`-d3`), and `--` ends option parsing.
+*/
+use std::{ffi::OsString, fs, path::PathBuf, time::Duration};
+
+use crate::size::{self, SizeFormat, Units};
+
+pub const USAGE: &str = "\
+Usage: pdu [OPTION]... [PATH]...
+  or:  pdu dupes [OPTION]... [PATH]...
+Report the size of every file and folder in each PATH (the current directory by default), or
+with 'dupes', list the files under the PATHs that have identical contents.
+
+Options:
+      --apparent-size  report apparent sizes (the number of bytes in each file) rather than
+                       the space allocated on disk
+      --both-sizes     report both the disk usage and the apparent size
+      --si             show sizes in powers of 1000 (kB, MB, ...) instead of 1024 (KiB, MiB, ...)
+      --bytes          show sizes as exact numbers of bytes
+      --precision=N    show sizes with N decimals (3 by default)
+      --block-size=SIZE
+                       show sizes as the number of SIZE-byte blocks, rounded up, like du;
+                       SIZE may have a unit, like 4K (4096 bytes) or 1MB (1000000 bytes)
+      --exclude=GLOB   skip files and folders matching GLOB, like a line of a .gitignore
+      --exclude-from=FILE
+                       skip files and folders matching any of the patterns in FILE
+      --gitignore      skip what .gitignore and .ignore files say to ignore, as well as
+                       what .pduignore files do (which is always)
+      --follow=WHEN    which symlinks to follow: 'never', 'cli' (the default) for the PATHs
+                       given on the command line, or 'always'; symlinks that aren't followed
+                       are counted as entries of their own
+  -P                   same as --follow=never
+  -H                   same as --follow=cli
+  -L                   same as --follow=always
+      --older-than=AGE only count files last modified more than AGE ago, where AGE is a
+                       number followed by s, m, h, d, w or y, like 90d
+      --newer-than=AGE only count files last modified less than AGE ago
+      --atime          make --older-than and --newer-than go by when files were last
+                       accessed instead
+  -x, --one-file-system
+                       don't go into folders that other filesystems are mounted on, and
+                       show them as mount points instead
+  -d, --max-depth=N    show every folder down to N levels below each PATH, as a tree
+      --sort=KEY       sort entries by 'size' (the default), 'name', 'mtime', 'count' (of
+                       files), 'dirs' (count of folders) or 'ext' (extension), in
+                       ascending order; ties are sorted by name
+      --by-ext         add up the files under the PATHs by extension, showing how many there
+                       are of each and the largest one, instead of listing folders
+      --by-type        add up the files under the PATHs by what's in them (ELF, gzip, PNG,
+                       SQLite, text, ...), going by their first 512 bytes
+      --by-owner       add up the files under the PATHs by the user that owns them
+      --by-group       add up the files under the PATHs by the group that owns them
+      --top=N          list the N largest files anywhere under each PATH, instead of what's
+                       directly inside it
+      --min-size=SIZE  fold entries smaller than SIZE, like 10MiB, into a single
+                       '(N others)' row
+      --limit=N        show only the N largest entries in each folder, folding the rest into
+                       a single '(N others)' row
+  -p, --percent        show how much of the total each entry makes up
+      --bars           show a bar for each entry, scaled to the largest one
+  -c, --counts         show how many files and folders each entry has in it
+      --newest         show when the newest file in each entry was last modified (in UTC)
+      --owner          show the user that owns each entry
+      --group          show the group that owns each entry
+  -r, --reverse        sort in descending order
+      --pin-total      keep the Total row at the bottom, even when sorting in reverse
+      --color=WHEN     color names by type (using LS_COLORS) and sizes by how big they are:
+                       'auto' (the default) colors them when writing to a terminal and
+                       NO_COLOR isn't set, 'always', or 'never'
+      --color-scale=SCALE
+                       what the colors of the sizes go by: 'magnitude' (the default), or
+                       'share' for how much of the total they make up
+      --icons=WHEN     show Nerd Font icons for entries with 'always' (which needs a Nerd
+                       Font); 'auto' (the default) and 'never' show '/', '@' and '*'
+                       markers instead
+  -i, --interactive    browse the results in the terminal, like ncdu
+      --json           print the results as a JSON document
+      --json-lines     print every entry as a separate line of JSON (NDJSON)
+  -j, --threads=N      read folders with N threads (by default, one per CPU core)
+      --count-links    count every hard link to a file, instead of counting the file once
+      --show-deduplicated
+                       report how much was left out of the totals for being a hard link to a
+                       file that was already counted
+  -m, --merge          report all PATHs in a single listing with a grand total
+  -h, --help           print this help and exit
+  -V, --version        print version information and exit
+";
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Options {
+    pub command: Command,
+    pub paths: Vec<PathBuf>,
+    pub merge: bool,
+    /// Which size to show and sort by.
+    pub size_kind: SizeKind,
+    pub both_sizes: bool,
+    pub size_format: SizeFormat,
+    pub count_links: bool,
+    pub show_deduplicated: bool,
+    /// Show a tree this many levels deep instead of a flat listing.
+    pub max_depth: Option<usize>,
+    pub top: Option<usize>,
+    /// Add up the files by something other than the folder they're in.
+    pub group_by: Option<GroupBy>,
+    /// Entries smaller than this are folded into a single row.
+    pub min_size: u64,
+    /// Only this many entries of each folder are listed, the rest are folded into a single row.
+    pub limit: Option<usize>,
+    /// Patterns for what to skip during the scan.
+    pub exclude: Vec<String>,
+    pub gitignore: bool,
+    pub one_file_system: bool,
+    pub follow: Follow,
+    /// Only count files last modified (or accessed) at least this long ago.
+    pub older_than: Option<Duration>,
+    /// Only count files last modified (or accessed) less than this long ago.
+    pub newer_than: Option<Duration>,
+    pub atime: bool,
+    pub sort: SortKey,
+    pub counts: bool,
+    pub newest: bool,
+    /// Show the user that owns each entry.
+    pub show_owner: bool,
+    /// Show the group that owns each entry.
+    pub show_group: bool,
+    pub percent: bool,
+    pub bars: bool,
+    pub reverse: bool,
+    pub pin_total: bool,
+    pub threads: Option<usize>,
+    pub format: Format,
+    pub interactive: bool,
+    pub icons: When,
+    pub color: When,
+    pub color_scale: ColorScale,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Command {
+    /// Report the size of everything.
+    #[default]
+    Report,
+    /// Find duplicate files.
+    Dupes,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GroupBy {
+    Extension,
+    /// What the first bytes of the file say it is.
+    ContentType,
+    /// The user that owns the file.
+    Owner,
+    /// The group that owns the file.
+    Group,
+}
+
+impl GroupBy {
+    /// The option that asks for it.
+    fn option(self) -> &'static str {
+        match self {
+            GroupBy::Extension => "--by-ext",
+            GroupBy::ContentType => "--by-type",
+            GroupBy::Owner => "--by-owner",
+            GroupBy::Group => "--by-group",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Follow {
+    Never,
+    /// Only the PATHs given on the command line.
+    #[default]
+    Cli,
+    Always,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    #[default]
+    Size,
+    Name,
+    /// When the entry itself was last modified.
+    Mtime,
+    /// How many files there are in it.
+    Count,
+    /// How many folders there are in it.
+    Dirs,
+    /// The extension, ignoring case.
+    Ext,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorScale {
+    /// By how big the size is.
+    #[default]
+    Magnitude,
+    /// By how much of the total the size is.
+    Share,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum When {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Format {
+    #[default]
+    Text,
+    Json,
+    JsonLines,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum SizeKind {
+    /// Space allocated on disk, like `du`.
+    #[default]
+    Disk,
+    /// Length of the file's contents, like `ls -l`.
+    Apparent,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Parsed {
+    Run(Box<Options>),
+    Help,
+    Version,
+}
+
+pub fn parse<I: IntoIterator<Item = OsString>>(args: I) -> Result<Parsed, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter().peekable();
+    // A folder called dupes can still be given as ./dupes.
+    if args.next_if(|arg| arg == "dupes").is_some() {
+        options.command = Command::Dupes;
+    }
+    let mut parser = Parser::new(args);
+    // Whether --sort was given at all, even for the default order.
+    let mut sort_given = false;
+
+    while let Some(arg) = parser.next()? {
+        match arg {
+            Arg::Positional(path) => options.paths.push(PathBuf::from(path)),
+            Arg::Short('m') | Arg::Long("merge") => options.merge = true,
+            Arg::Long("apparent-size") => options.size_kind = SizeKind::Apparent,
+            Arg::Long("both-sizes") => options.both_sizes = true,
+            Arg::Long("si") => options.size_format.units = Units::Si,
+            Arg::Long("bytes") => options.size_format.units = Units::Bytes,
+            Arg::Long("precision") => {
+                options.size_format.precision = parse_number(&parser.value()?, "precision")?
+            }
+            Arg::Long("block-size") => {
+                let value = parser.value()?;
+                let value = value.to_str().ok_or("invalid block size")?;
+                options.size_format.units = Units::Blocks(size::parse_block_size(value)?);
+            }
+            Arg::Long("count-links") => options.count_links = true,
+            Arg::Long("show-deduplicated") => options.show_deduplicated = true,
+            Arg::Short('d') | Arg::Long("max-depth") => {
+                let depth = parse_number(&parser.value()?, "max-depth")?;
+                if depth == 0 {
+                    return Err("--max-depth must be at least 1".to_owned());
+                }
+                options.max_depth = Some(depth);
+            }
+            Arg::Long("exclude") => {
+                let pattern = parser.value()?;
+                let pattern = pattern
+                    .to_str()
+                    .ok_or("--exclude patterns must be valid UTF-8")?;
+                options.exclude.push(pattern.to_owned());
+            }
+            Arg::Long("exclude-from") => {
+                let file = parser.value()?;
+                let patterns = fs::read_to_string(&file)
+                    .map_err(|e| format!("cannot read '{}': {}", file.to_string_lossy(), e))?;
+                options.exclude.extend(patterns.lines().map(str::to_owned));
+            }
+            Arg::Long("gitignore") => options.gitignore = true,
+            Arg::Short('x') | Arg::Long("one-file-system") => options.one_file_system = true,
+            Arg::Long("follow") => {
+                let value = parser.value()?;
+                options.follow = match value.to_str() {
+                    Some("never") => Follow::Never,
+                    Some("cli") => Follow::Cli,
+                    Some("always") => Follow::Always,
+                    _ => {
+                        return Err(format!(
+                            "invalid value '{}' for --follow, expected 'never', 'cli' or 'always'",
+                            value.to_string_lossy()
+                        ))
+                    }
+                }
+            }
+            Arg::Long("older-than") => options.older_than = Some(parse_age(&parser.value()?)?),
+            Arg::Long("newer-than") => options.newer_than = Some(parse_age(&parser.value()?)?),
+            Arg::Long("atime") => options.atime = true,
+            Arg::Short('P') => options.follow = Follow::Never,
+            Arg::Short('H') => options.follow = Follow::Cli,
+            Arg::Short('L') => options.follow = Follow::Always,
+            Arg::Long("sort") => {
+                sort_given = true;
+                let value = parser.value()?;
+                options.sort = match value.to_str() {
+                    Some("size") => SortKey::Size,
+                    Some("name") => SortKey::Name,
+                    Some("mtime") | Some("time") => SortKey::Mtime,
+                    Some("count") | Some("files") => SortKey::Count,
+                    Some("dirs") => SortKey::Dirs,
+                    Some("ext") | Some("extension") => SortKey::Ext,
+                    _ => {
+                        return Err(format!(
+                        "invalid sort key '{}', expected 'size', 'name', 'mtime', 'count', 'dirs' or 'ext'",
+                        value.to_string_lossy()
+                    ))
+                    }
+                }
+            }
+            Arg::Short('c') | Arg::Long("counts") => options.counts = true,
+            Arg::Long("newest") => options.newest = true,
+            Arg::Long("owner") => options.show_owner = true,
+            Arg::Long("group") => options.show_group = true,
+            Arg::Short('p') | Arg::Long("percent") => options.percent = true,
+            Arg::Long("bars") => options.bars = true,
+            Arg::Short('r') | Arg::Long("reverse") => options.reverse = true,
+            Arg::Long("pin-total") => options.pin_total = true,
+            Arg::Long("min-size") => {
+                let value = parser.value()?;
+                let value = value.to_str().ok_or("invalid size")?;
+                options.min_size = size::parse_size(value)?;
+            }
+            Arg::Long("limit") => {
+                let limit = parse_number(&parser.value()?, "limit")?;
+                if limit == 0 {
+                    return Err("--limit must be at least 1".to_owned());
+                }
+                options.limit = Some(limit);
+            }
+            Arg::Long("by-ext") => options.group_by = Some(GroupBy::Extension),
+            Arg::Long("by-type") => options.group_by = Some(GroupBy::ContentType),
+            Arg::Long("by-owner") => options.group_by = Some(GroupBy::Owner),
+            Arg::Long("by-group") => options.group_by = Some(GroupBy::Group),
+            Arg::Long("top") => {
+                let top = parse_number(&parser.value()?, "top")?;
+                if top == 0 {
+                    return Err("--top must be at least 1".to_owned());
+                }
+                options.top = Some(top);
+            }
+            Arg::Short('i') | Arg::Long("interactive") => options.interactive = true,
+            Arg::Long("icons") => options.icons = parse_when(&parser.value()?, "icons")?,
+            Arg::Long("color") | Arg::Long("colour") => {
+                options.color = parse_when(&parser.value()?, "color")?
+            }
+            Arg::Long("color-scale") => {
+                options.color_scale = match parser.value()?.to_str() {
+                    Some("magnitude") => ColorScale::Magnitude,
+                    Some("share") => ColorScale::Share,
+                    _ => return Err("--color-scale must be 'magnitude' or 'share'".to_owned()),
+                }
+            }
+            Arg::Long("json") => options.format = Format::Json,
+            Arg::Long("json-lines") | Arg::Long("ndjson") => options.format = Format::JsonLines,
+            Arg::Short('j') | Arg::Long("threads") => {
+                let threads = parse_number(&parser.value()?, "threads")?;
+                if threads == 0 {
+                    return Err("--threads must be at least 1".to_owned());
+                }
+                options.threads = Some(threads);
+            }
+            Arg::Short('h') | Arg::Long("help") => return Ok(Parsed::Help),
+            Arg::Short('V') | Arg::Long("version") => return Ok(Parsed::Version),
+            arg => return Err(format!("unrecognized option '{}'", arg)),
+        }
+    }
+
+    if options.top.is_some() && options.max_depth.is_some() {
+        return Err("--top and --max-depth can't be used together".to_owned());
+    }
+    if let Some(group_by) = options.group_by {
+        if options.command == Command::Dupes {
+            return Err(format!(
+                "{} can't be used with pdu dupes",
+                group_by.option()
+            ));
+        }
+        if matches!(options.sort, SortKey::Mtime | SortKey::Dirs | SortKey::Ext) {
+            return Err(format!(
+                "{} can only be sorted by 'size', 'name' or 'count'",
+                group_by.option()
+            ));
+        }
+        // Grouping replaces the listing, so nothing that changes the listing makes sense with it.
+        let listing_options = [
+            (options.top.is_some(), "--top"),
+            (options.max_depth.is_some(), "--max-depth"),
+            (options.interactive, "--interactive"),
+            (options.show_owner, "--owner"),
+            (options.show_group, "--group"),
+        ];
+        if let Some((_, other)) = listing_options.iter().find(|(used, _)| *used) {
+            return Err(format!(
+                "{} and {} can't be used together",
+                group_by.option(),
+                other
+            ));
+        }
+    }
+    if options.command == Command::Dupes {
+        // The duplicates are listed on their own, so nothing that changes the listing applies.
+        let listing_options = [
+            (options.merge, "--merge"),
+            (options.show_deduplicated, "--show-deduplicated"),
+            (options.max_depth.is_some(), "--max-depth"),
+            (sort_given, "--sort"),
+            (options.top.is_some(), "--top"),
+            (options.min_size > 0, "--min-size"),
+            (options.limit.is_some(), "--limit"),
+            (options.percent, "--percent"),
+            (options.bars, "--bars"),
+            (options.counts, "--counts"),
+            (options.newest, "--newest"),
+            (options.show_owner, "--owner"),
+            (options.show_group, "--group"),
+            (options.reverse, "--reverse"),
+            (options.pin_total, "--pin-total"),
+            (options.interactive, "--interactive"),
+        ];
+        if let Some((_, other)) = listing_options.iter().find(|(used, _)| *used) {
+            return Err(format!("{} can't be used with pdu dupes", other));
+        }
+    }
+    if options.paths.is_empty() {
+        options.paths.push(PathBuf::from("."));
+    }
+
+    Ok(Parsed::Run(Box::new(options)))
+}
+
+fn parse_number(value: &OsString, option: &str) -> Result<usize, String> {
+    value.to_str().and_then(|v| v.parse().ok()).ok_or_else(|| {
+        format!(
+            "invalid number '{}' for --{}",
+            value.to_string_lossy(),
+            option
+        )
+    })
+}
+
+/// Parse an age like 90d: a number followed by s (seconds), m (minutes), h (hours), d (days),
+/// w (weeks) or y (years of 365 days).
+fn parse_age(value: &OsString) -> Result<Duration, String> {
+    let invalid = || {
+        format!(
+            "invalid age '{}', expected a number followed by s, m, h, d, w or y, like 90d",
+            value.to_string_lossy()
+        )
+    };
+    let text = value.to_str().ok_or_else(invalid)?;
+    let split = text
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(invalid)?;
+    let (number, unit) = text.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        "y" => 365 * 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+fn parse_when(value: &OsString, option: &str) -> Result<When, String> {
+    match value.to_str() {
+        Some("auto") => Ok(When::Auto),
+        Some("always") => Ok(When::Always),
+        Some("never") => Ok(When::Never),
+        _ => Err(format!(
+            "invalid value '{}' for --{}, expected 'auto', 'always' or 'never'",
+            value.to_string_lossy(),
+            option
+        )),
+    }
+}
+
+#[derive(Debug)]
+enum Arg<'a> {
+    Short(char),
+    Long(&'a str),
+    Positional(OsString),
+}
+
+impl std::fmt::Display for Arg<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Arg::Short(c) => write!(f, "-{}", c),
+            Arg::Long(name) => write!(f, "--{}", name),
+            Arg::Positional(value) => write!(f, "{}", value.to_string_lossy()),
+        }
+    }
+}
+
+struct Parser {
+    args: std::vec::IntoIter<OsString>,
+    /// The name of the last long option returned, e.g. `--depth` from `--depth=3`.
+    long: String,
+    /// The value attached to the last long option with `=`, if it hasn't been consumed yet.
+    inline_value: Option<OsString>,
+    /// Characters left over from a group of short options such as `-abc`.
+    shorts: Vec<char>,
+    only_positionals: bool,
+}
+
+impl Parser {
+    fn new<I: IntoIterator<Item = OsString>>(args: I) -> Self {
+        Parser {
+            args: args.into_iter().collect::<Vec<_>>().into_iter(),
+            long: String::new(),
+            inline_value: None,
+            shorts: vec![],
+            only_positionals: false,
+        }
+    }
+
+    fn next(&mut self) -> Result<Option<Arg<'_>>, String> {
+        if self.inline_value.take().is_some() {
+            return Err(format!(
+                "option '--{}' doesn't allow an argument",
+                self.long
+            ));
+        }
+        if !self.shorts.is_empty() {
+            return Ok(Some(Arg::Short(self.shorts.remove(0))));
+        }
+
+        let arg = match self.args.next() {
+            None => return Ok(None),
+            Some(arg) => arg,
+        };
+        if self.only_positionals {
+            return Ok(Some(Arg::Positional(arg)));
+        }
+
+        // Options are always valid unicode, anything else must be a path.
+        let text = match arg.to_str() {
+            Some(text) => text,
+            None => return Ok(Some(Arg::Positional(arg))),
+        };
+        if text == "--" {
+            self.only_positionals = true;
+            return self.next();
+        } else if let Some(long) = text.strip_prefix("--") {
+            match long.split_once('=') {
+                Some((name, value)) => {
+                    self.long = name.to_owned();
+                    self.inline_value = Some(OsString::from(value));
+                }
+                None => self.long = long.to_owned(),
+            }
+            return Ok(Some(Arg::Long(&self.long)));
+        } else if text.len() > 1 && text.starts_with('-') {
+            self.shorts = text.chars().skip(1).collect();
+            return Ok(Some(Arg::Short(self.shorts.remove(0))));
+        }
+
+        Ok(Some(Arg::Positional(arg)))
+    }
+
+    /// Get the value for the option that was just returned by `next`.
+    fn value(&mut self) -> Result<OsString, String> {
+        if let Some(value) = self.inline_value.take() {
+            return Ok(value);
+        }
+        if !self.shorts.is_empty() {
+            return Ok(self.shorts.drain(..).collect::<String>().into());
+        }
+        self.args
+            .next()
+            .ok_or_else(|| "option requires an argument".to_owned())
+    }
+}
+
+#[cfg(test)]
+fn parse_strs(args: &[&str]) -> Result<Parsed, String> {
+    parse(args.iter().map(OsString::from))
+}
+
+#[test]
+fn no_paths_means_current_directory() {
+    let expected = Options {
+        paths: vec![PathBuf::from(".")],
+        ..Options::default()
+    };
+    assert_eq!(parse_strs(&[]), Ok(Parsed::Run(Box::new(expected))));
+}
+
+#[test]
+fn paths_and_flags_can_be_mixed() {
+    let expected = Options {
+        paths: vec![PathBuf::from("/mnt/a"), PathBuf::from("-b")],
+        merge: true,
+        ..Options::default()
+    };
+    assert_eq!(
+        parse_strs(&["/mnt/a", "--merge", "--", "-b"]),
+        Ok(Parsed::Run(Box::new(expected)))
+    );
+}
+
+#[test]
+fn unknown_options_are_rejected() {
+    assert!(parse_strs(&["--bogus"]).is_err());
+    assert!(parse_strs(&["-mz"]).is_err());
+    assert!(parse_strs(&["--merge=yes"]).is_err());
+}
+
+#[test]
+fn option_values_can_be_attached_or_separate() {
+    for args in [
+        &["--max-depth", "3"][..],
+        &["--max-depth=3"],
+        &["-d", "3"],
+        &["-d3"],
+    ] {
+        match parse_strs(args) {
+            Ok(Parsed::Run(options)) => assert_eq!(options.max_depth, Some(3)),
+            other => panic!("{:?} parsed as {:?}", args, other),
+        }
+    }
+    assert!(parse_strs(&["--max-depth"]).is_err());
+    assert!(parse_strs(&["--max-depth=three"]).is_err());
+    assert_eq!(
+        parse_strs(&["--top=0"]),
+        Err("--top must be at least 1".to_owned())
+    );
+    assert_eq!(
+        parse_strs(&["--limit", "0"]),
+        Err("--limit must be at least 1".to_owned())
+    );
+}
+
+#[test]
+fn ages() {
+    let age = |value: &str| parse_age(&OsString::from(value));
+    assert_eq!(age("90d"), Ok(Duration::from_secs(90 * 24 * 60 * 60)));
+    assert_eq!(age("1w"), Ok(Duration::from_secs(7 * 24 * 60 * 60)));
+    assert_eq!(age("30s"), Ok(Duration::from_secs(30)));
+    assert!(age("90").is_err());
+    assert!(age("d").is_err());
+    assert!(age("1.5d").is_err());
+    assert!(age("3x").is_err());
+}
+
+#[test]
+fn dupes_is_only_a_command_at_the_start() {
+    match parse_strs(&["dupes", "a"]) {
+        Ok(Parsed::Run(options)) => {
+            assert_eq!(options.command, Command::Dupes);
+            assert_eq!(options.paths, [PathBuf::from("a")]);
+        }
+        other => panic!("parsed as {:?}", other),
+    }
+    match parse_strs(&["a", "dupes"]) {
+        Ok(Parsed::Run(options)) => {
+            assert_eq!(options.command, Command::Report);
+            assert_eq!(options.paths, [PathBuf::from("a"), PathBuf::from("dupes")]);
+        }
+        other => panic!("parsed as {:?}", other),
+    }
+}
+
+#[test]
+fn grouping_rejects_listing_options() {
+    assert_eq!(
+        parse_strs(&["--by-ext", "--top", "5"]),
+        Err("--by-ext and --top can't be used together".to_owned())
+    );
+    assert_eq!(
+        parse_strs(&["-d2", "--by-type"]),
+        Err("--by-type and --max-depth can't be used together".to_owned())
+    );
+    for other in ["-i", "--owner", "--group"] {
+        assert!(parse_strs(&["--by-owner", other]).is_err());
+        assert!(parse_strs(&[other, "--by-group"]).is_err());
+    }
+    assert_eq!(
+        parse_strs(&["dupes", "--by-group"]),
+        Err("--by-group can't be used with pdu dupes".to_owned())
+    );
+    assert_eq!(
+        parse_strs(&["--by-ext", "--sort=mtime"]),
+        Err("--by-ext can only be sorted by 'size', 'name' or 'count'".to_owned())
+    );
+    assert!(parse_strs(&["--by-ext", "--sort=name", "--apparent-size"]).is_ok());
+}
+
+#[test]
+fn dupes_rejects_listing_options() {
+    assert_eq!(
+        parse_strs(&["dupes", "--top", "2"]),
+        Err("--top can't be used with pdu dupes".to_owned())
+    );
+    for option in [
+        "-d2",
+        "--sort=size",
+        "--min-size=1G",
+        "--limit=3",
+        "-p",
+        "--bars",
+        "-c",
+        "--owner",
+        "-i",
+    ] {
+        assert!(parse_strs(&["dupes", option]).is_err(), "{}", option);
+    }
+    assert!(parse_strs(&["dupes", "--apparent-size", "-j2", "-x"]).is_ok());
+}
@@ -0,0 +1,233 @@
+/**
+* Colored output.
+*
+* Names are colored the same way `ls` colors them, from the LS_COLORS environment variable (as
+* set up by `dircolors`), with the same defaults `ls` uses when it isn't set. Sizes are colored on
+* a scale from green to red, either by how big they are or by how much of the total they make up.
+*
+* Colors are only used when writing to a terminal, unless asked for with --color=always, and
+* setting NO_COLOR turns them off unless asked for (see https://no-color.org).
+*/
+use std::{
+    env,
+    io::{self, IsTerminal},
+};
+
+use term_grid::Cell;
+
+use crate::{
+    args::{ColorScale, Options, When},
+    EntryKind, PathData,
+};
+
+/// What `ls` uses when LS_COLORS isn't set.
+const DEFAULT_LS_COLORS: &str =
+    "di=01;34:ln=01;36:ex=01;32:pi=40;33:so=01;35:bd=40;33;01:cd=40;33;01";
+
+/// Sizes from small to large.
+const SIZE_SCALE: [&str; 4] = ["32", "33", "38;5;208", "01;31"];
+
+pub struct Colors {
+    enabled: bool,
+    ls_colors: LsColors,
+    scale: ColorScale,
+}
+
+impl Colors {
+    pub fn new(options: &Options) -> Self {
+        let enabled = match options.color {
+            When::Always => true,
+            When::Never => false,
+            When::Auto => {
+                io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        };
+        let ls_colors = match env::var("LS_COLORS") {
+            Ok(value) if !value.is_empty() => LsColors::parse(&value),
+            _ => LsColors::parse(DEFAULT_LS_COLORS),
+        };
+        Colors {
+            enabled,
+            ls_colors,
+            scale: options.color_scale,
+        }
+    }
+
+    /// The style for an entry's name.
+    pub fn name(&self, d: &PathData) -> Option<&str> {
+        if !self.enabled {
+            return None;
+        }
+        self.ls_colors.style(d)
+    }
+
+    /// The style for a size, given the total it's a part of.
+    pub fn size(&self, size: u64, total: u64) -> Option<&'static str> {
+        if !self.enabled {
+            return None;
+        }
+        let step = match self.scale {
+            // Anything under a MiB, MiBs, GiBs, and TiBs or more.
+            ColorScale::Magnitude => {
+                if size < 1 << 20 {
+                    0
+                } else if size < 1 << 30 {
+                    1
+                } else if size < 1 << 40 {
+                    2
+                } else {
+                    3
+                }
+            }
+            ColorScale::Share => {
+                let share = size as f64 / total.max(1) as f64;
+                if share < 0.01 {
+                    0
+                } else if share < 0.1 {
+                    1
+                } else if share < 0.5 {
+                    2
+                } else {
+                    3
+                }
+            }
+        };
+        Some(SIZE_SCALE[step])
+    }
+
+    /// The style for the Total row's name.
+    pub fn total(&self) -> Option<&'static str> {
+        self.enabled.then_some("01")
+    }
+}
+
+/// A grid cell whose width doesn't include the escape codes used to color it.
+pub fn cell(text: String, style: Option<&str>) -> Cell {
+    let mut cell = Cell::from(text);
+    if let Some(style) = style {
+        cell.contents = paint(&cell.contents, style);
+    }
+    cell
+}
+
+pub fn paint(text: &str, style: &str) -> String {
+    format!("\x1b[{}m{}\x1b[0m", style, text)
+}
+
+/// The parts of LS_COLORS we know how to use.
+#[derive(Debug, Default)]
+struct LsColors {
+    directory: Option<String>,
+    symlink: Option<String>,
+    fifo: Option<String>,
+    socket: Option<String>,
+    block_device: Option<String>,
+    char_device: Option<String>,
+    executable: Option<String>,
+    file: Option<String>,
+    /// Styles for names ending in something, like `*.tar`, with the ending in lowercase.
+    suffixes: Vec<(String, String)>,
+}
+
+impl LsColors {
+    fn parse(value: &str) -> Self {
+        let mut colors = LsColors::default();
+        for (key, style) in value.split(':').filter_map(|item| item.split_once('=')) {
+            let style = style.to_owned();
+            match key {
+                "di" => colors.directory = Some(style),
+                "ln" => colors.symlink = Some(style),
+                "pi" => colors.fifo = Some(style),
+                "so" => colors.socket = Some(style),
+                "bd" => colors.block_device = Some(style),
+                "cd" => colors.char_device = Some(style),
+                "ex" => colors.executable = Some(style),
+                "fi" => colors.file = Some(style),
+                _ => {
+                    if let Some(suffix) = key.strip_prefix('*') {
+                        colors.suffixes.push((suffix.to_lowercase(), style));
+                    }
+                }
+            }
+        }
+        // Try the longest endings first, so that *.tar.gz wins over *.gz.
+        colors
+            .suffixes
+            .sort_by_key(|(suffix, _)| usize::MAX - suffix.len());
+        colors
+    }
+
+    fn style(&self, d: &PathData) -> Option<&str> {
+        let style = match d.kind {
+            EntryKind::Directory => &self.directory,
+            EntryKind::Symlink => &self.symlink,
+            EntryKind::Fifo => &self.fifo,
+            EntryKind::Socket => &self.socket,
+            EntryKind::BlockDevice => &self.block_device,
+            EntryKind::CharDevice => &self.char_device,
+            EntryKind::File if d.executable => &self.executable,
+            EntryKind::File => {
+                let name = d.name.to_string_lossy().to_lowercase();
+                match self
+                    .suffixes
+                    .iter()
+                    .find(|(suffix, _)| name.ends_with(suffix))
+                {
+                    Some((_, style)) => return Some(style),
+                    None => &self.file,
+                }
+            }
+        };
+        style.as_deref()
+    }
+}
+
+#[test]
+fn ls_colors_by_type_and_extension() {
+    use std::ffi::OsString;
+
+    let colors = LsColors::parse("di=01;34:ex=01;32:*.gz=31:*.tar.gz=35:*README=33:bogus");
+    let entry = |name: &str, kind| PathData::new(OsString::from(name), kind, Default::default());
+
+    assert_eq!(
+        colors.style(&entry("src", EntryKind::Directory)),
+        Some("01;34")
+    );
+    assert_eq!(colors.style(&entry("a.GZ", EntryKind::File)), Some("31"));
+    assert_eq!(
+        colors.style(&entry("a.tar.gz", EntryKind::File)),
+        Some("35")
+    );
+    assert_eq!(colors.style(&entry("README", EntryKind::File)), Some("33"));
+    assert_eq!(colors.style(&entry("main.rs", EntryKind::File)), None);
+
+    let mut script = entry("run.gz", EntryKind::File);
+    script.executable = true;
+    assert_eq!(colors.style(&script), Some("01;32"));
+}
+
+#[test]
+fn size_scales() {
+    let colors = |scale| Colors {
+        enabled: true,
+        ls_colors: LsColors::default(),
+        scale,
+    };
+    let magnitude = colors(ColorScale::Magnitude);
+    assert_eq!(magnitude.size(1000, 0), Some("32"));
+    assert_eq!(magnitude.size(5 << 20, 0), Some("33"));
+    assert_eq!(magnitude.size(5 << 30, 0), Some("38;5;208"));
+    assert_eq!(magnitude.size(5 << 40, 0), Some("01;31"));
+
+    let share = colors(ColorScale::Share);
+    assert_eq!(share.size(1, 1000), Some("32"));
+    assert_eq!(share.size(600, 1000), Some("01;31"));
+    assert_eq!(share.size(0, 0), Some("32"));
+}
+
+#[test]
+fn cells_are_as_wide_as_their_text() {
+    let cell = cell("hello".to_owned(), Some("01;34"));
+    assert_eq!(cell.width, 5);
+    assert_eq!(cell.contents, "\x1b[01;34mhello\x1b[0m");
+}
@@ -0,0 +1,389 @@
+/**
+* Finding duplicate files (`pdu dupes`).
+*
+* The scan collects every file it counts, and then the candidates are narrowed down in stages,
+* each more expensive than the last but run on fewer files: files can only be identical if they're
+* the same size, files of the same size whose first few KiB differ can't be identical, and
+* whatever is left gets its whole contents hashed. Hard links to a file are the same file rather
+* than copies of it, so only one of them is ever considered.
+*
+* The hashes only narrow things down: they're made with the standard library's hasher, which has a
+* fixed key, so files that aren't identical could be made to collide on purpose. Every set of files
+* whose hashes match is compared byte for byte before it's reported.
+*/
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    hash::{DefaultHasher, Hasher},
+    io::{self, Read},
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use crate::{args::SizeKind, error::ScanError, scan::Size};
+
+/// How much of each file is hashed in the first pass.
+const PARTIAL_HASH_SIZE: u64 = 4096;
+
+/// A file found by the scan.
+#[derive(Debug)]
+pub struct FileInfo {
+    pub path: PathBuf,
+    pub size: Size,
+    /// The device and inode, so that hard links can be recognized.
+    pub inode: Option<(u64, u64)>,
+}
+
+/// Files with identical contents.
+#[derive(Debug)]
+pub struct DuplicateSet {
+    /// The size of each of the files.
+    pub size: Size,
+    /// Sorted, so the output doesn't depend on the order the files were found in.
+    pub paths: Vec<PathBuf>,
+}
+
+impl DuplicateSet {
+    /// The space that deleting all but one of the files would free up.
+    pub fn reclaimable(&self) -> Size {
+        let copies = self.paths.len() as u64 - 1;
+        Size {
+            apparent: self.size.apparent.saturating_mul(copies),
+            disk: self.size.disk.saturating_mul(copies),
+        }
+    }
+}
+
+/// Find the sets of identical files, hashing with `threads` threads, sorted by how much space
+/// they take up for nothing. Files that can't be read are left out and added to `errors`.
+pub fn find(
+    mut files: Vec<FileInfo>,
+    size_kind: SizeKind,
+    threads: usize,
+    errors: &mut Vec<ScanError>,
+) -> Vec<DuplicateSet> {
+    // Of several hard links to a file, the one that sorts first is kept.
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut seen = HashSet::new();
+    let mut by_size: HashMap<u64, Vec<FileInfo>> = HashMap::new();
+    for file in files {
+        // Empty files are all the same, but there's nothing to gain from deleting them.
+        if file.size.apparent == 0 || file.inode.is_some_and(|inode| !seen.insert(inode)) {
+            continue;
+        }
+        by_size.entry(file.size.apparent).or_default().push(file);
+    }
+    let candidates: Vec<Vec<FileInfo>> = by_size.into_values().filter(|g| g.len() > 1).collect();
+
+    let candidates = split_by_hash(candidates, Some(PARTIAL_HASH_SIZE), threads, errors);
+    // Files no bigger than the partial hash have already been hashed in full.
+    let (small, large): (Vec<_>, Vec<_>) = candidates
+        .into_iter()
+        .partition(|g| g[0].size.apparent <= PARTIAL_HASH_SIZE);
+    let large = split_by_hash(large, None, threads, errors);
+    let confirmed = confirm(small.into_iter().chain(large).collect(), threads, errors);
+
+    let mut sets: Vec<DuplicateSet> = confirmed
+        .into_iter()
+        .map(|group| {
+            let size = group[0].size;
+            let mut paths: Vec<PathBuf> = group.into_iter().map(|f| f.path).collect();
+            paths.sort();
+            DuplicateSet { size, paths }
+        })
+        .collect();
+    sets.sort_by(|a, b| {
+        let wasted = |set: &DuplicateSet| set.reclaimable().get(size_kind);
+        wasted(a)
+            .cmp(&wasted(b))
+            .then_with(|| a.paths.cmp(&b.paths))
+    });
+    sets
+}
+
+/// Split every group of files into groups with the same hash of their first `limit` bytes (or of
+/// all of them), dropping any files left without a match.
+fn split_by_hash(
+    groups: Vec<Vec<FileInfo>>,
+    limit: Option<u64>,
+    threads: usize,
+    errors: &mut Vec<ScanError>,
+) -> Vec<Vec<FileInfo>> {
+    let files: Vec<(usize, FileInfo)> = groups
+        .into_iter()
+        .enumerate()
+        .flat_map(|(group, files)| files.into_iter().map(move |f| (group, f)))
+        .collect();
+
+    // Each thread takes the next file that no one has hashed yet.
+    let next = AtomicUsize::new(0);
+    let hashes: Vec<Mutex<Option<io::Result<u128>>>> =
+        files.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some((_, file)) = files.get(i) else {
+                    break;
+                };
+                *hashes[i].lock().unwrap() = Some(hash_file(file, limit));
+            });
+        }
+    });
+
+    let mut split: HashMap<(usize, u128), Vec<FileInfo>> = HashMap::new();
+    for ((group, file), hash) in files.into_iter().zip(hashes) {
+        match hash.into_inner().unwrap().expect("every file is hashed") {
+            Ok(hash) => split.entry((group, hash)).or_default().push(file),
+            Err(e) => errors.push(ScanError::new(&file.path, e)),
+        }
+    }
+    split.into_values().filter(|g| g.len() > 1).collect()
+}
+
+/// Split every group of files with matching hashes into the sets of files that really are
+/// identical, comparing them byte for byte with `threads` threads.
+fn confirm(
+    groups: Vec<Vec<FileInfo>>,
+    threads: usize,
+    errors: &mut Vec<ScanError>,
+) -> Vec<Vec<FileInfo>> {
+    // Each thread takes the next group that no one has compared yet.
+    let next = AtomicUsize::new(0);
+    let groups: Vec<Mutex<Vec<FileInfo>>> = groups.into_iter().map(Mutex::new).collect();
+    let results: Vec<Mutex<Option<Comparison>>> = groups.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(group) = groups.get(i) else {
+                    break;
+                };
+                let files = mem::take(&mut *group.lock().unwrap());
+                *results[i].lock().unwrap() = Some(split_identical(files));
+            });
+        }
+    });
+
+    let mut sets = vec![];
+    for result in results {
+        let comparison = result
+            .into_inner()
+            .unwrap()
+            .expect("every group is compared");
+        sets.extend(comparison.sets);
+        errors.extend(comparison.errors);
+    }
+    sets
+}
+
+/// The files of one group that turned out to be identical, and the ones that couldn't be read.
+struct Comparison {
+    sets: Vec<Vec<FileInfo>>,
+    errors: Vec<ScanError>,
+}
+
+/// Sort files into sets of identical ones, leaving out any that aren't identical to another.
+fn split_identical(mut files: Vec<FileInfo>) -> Comparison {
+    let mut sets = vec![];
+    let mut errors = vec![];
+    while files.len() > 1 {
+        let first = files.remove(0);
+        let mut same = vec![];
+        let mut different = vec![];
+        let mut rest = files.into_iter();
+        while let Some(file) = rest.next() {
+            match same_contents(&first.path, &file.path) {
+                Ok(true) => same.push(file),
+                Ok(false) => different.push(file),
+                Err(e) if e.path == first.path => {
+                    // Without the first file there's nothing to compare to, so the others go
+                    // back to be compared with each other.
+                    errors.push(e);
+                    different.append(&mut same);
+                    different.push(file);
+                    different.extend(rest.by_ref());
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+        if !same.is_empty() {
+            same.insert(0, first);
+            sets.push(same);
+        }
+        files = different;
+    }
+    Comparison { sets, errors }
+}
+
+/// Whether two files have the same contents, going by the bytes themselves.
+fn same_contents(a: &Path, b: &Path) -> Result<bool, ScanError> {
+    let open = |path: &Path| File::open(path).map_err(|e| ScanError::new(path, e));
+    let (mut a_file, mut b_file) = (open(a)?, open(b)?);
+    let mut a_buf = vec![0u8; 64 * 1024];
+    let mut b_buf = vec![0u8; 64 * 1024];
+    loop {
+        let a_len = fill(&mut a_file, &mut a_buf).map_err(|e| ScanError::new(a, e))?;
+        let b_len = fill(&mut b_file, &mut b_buf).map_err(|e| ScanError::new(b, e))?;
+        if a_buf[..a_len] != b_buf[..b_len] {
+            return Ok(false);
+        }
+        if a_len == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Read until the buffer is full or the file ends, returning how much was read.
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut len = 0;
+    while len < buf.len() {
+        match reader.read(&mut buf[len..]) {
+            Ok(0) => break,
+            Ok(n) => len += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(len)
+}
+
+fn hash_file(file: &FileInfo, limit: Option<u64>) -> io::Result<u128> {
+    let mut reader: Box<dyn Read> = match limit {
+        Some(limit) => Box::new(File::open(&file.path)?.take(limit)),
+        None => Box::new(File::open(&file.path)?),
+    };
+    let mut hasher = ContentHasher::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        hasher.write(&buf[..n]);
+    }
+    Ok(hasher.finish())
+}
+
+/// A 128-bit hash made from two 64-bit ones.
+struct ContentHasher {
+    low: DefaultHasher,
+    high: DefaultHasher,
+}
+
+impl ContentHasher {
+    fn new() -> Self {
+        let mut high = DefaultHasher::new();
+        // Any difference in the starting state gives an unrelated hash.
+        high.write_u8(0xff);
+        ContentHasher {
+            low: DefaultHasher::new(),
+            high,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.low.write(bytes);
+        self.high.write(bytes);
+    }
+
+    fn finish(&self) -> u128 {
+        (u128::from(self.high.finish()) << 64) | u128::from(self.low.finish())
+    }
+}
+
+#[test]
+fn identical_files_are_grouped() {
+    let dir = crate::scan::temp_dir("dupes");
+    let big = |fill: u8, last: u8| {
+        let mut contents = vec![fill; 10_000];
+        contents[9_999] = last;
+        contents
+    };
+    let files = [
+        ("a", big(1, 0)),
+        ("b", big(1, 0)),
+        // Same size and start as a and b, but not the same.
+        ("c", big(1, 1)),
+        ("d", b"small".to_vec()),
+        ("e", b"small".to_vec()),
+        ("f", b"other".to_vec()),
+        ("g", vec![]),
+        ("h", vec![]),
+    ];
+    let mut infos = vec![];
+    for (i, (name, contents)) in files.iter().enumerate() {
+        std::fs::write(dir.join(name), contents).unwrap();
+        let size = contents.len() as u64;
+        infos.push(FileInfo {
+            path: dir.join(name),
+            size: Size {
+                apparent: size,
+                disk: size,
+            },
+            inode: Some((0, i as u64)),
+        });
+    }
+    // Another hard link to `a`.
+    infos.push(FileInfo {
+        path: dir.join("a2"),
+        size: infos[0].size,
+        inode: infos[0].inode,
+    });
+
+    let mut errors = vec![];
+    let sets = find(infos, SizeKind::Apparent, 2, &mut errors);
+    assert!(errors.is_empty());
+    let paths: Vec<Vec<PathBuf>> = sets.iter().map(|s| s.paths.clone()).collect();
+    assert_eq!(
+        paths,
+        [
+            vec![dir.join("d"), dir.join("e")],
+            vec![dir.join("a"), dir.join("b")]
+        ]
+    );
+    assert_eq!(sets[1].reclaimable().apparent, 10_000);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn matching_hashes_are_confirmed_byte_for_byte() {
+    let dir = crate::scan::temp_dir("dupes-confirm");
+    let mut files = vec![];
+    // As if they all had the same hash, with the one that can't be read first.
+    for (name, contents) in [
+        ("gone", None),
+        ("a", Some("same")),
+        ("b", Some("diff")),
+        ("c", Some("same")),
+    ] {
+        if let Some(contents) = contents {
+            std::fs::write(dir.join(name), contents).unwrap();
+        }
+        files.push(FileInfo {
+            path: dir.join(name),
+            size: Size::default(),
+            inode: None,
+        });
+    }
+
+    let comparison = split_identical(files);
+    let sets: Vec<Vec<PathBuf>> = comparison
+        .sets
+        .into_iter()
+        .map(|set| set.into_iter().map(|f| f.path).collect())
+        .collect();
+    assert_eq!(sets, [vec![dir.join("a"), dir.join("c")]]);
+    assert_eq!(comparison.errors.len(), 1);
+    assert_eq!(comparison.errors[0].path, dir.join("gone"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
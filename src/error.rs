@@ -0,0 +1,33 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// Something under one of the roots that couldn't be read, e.g. because of missing permissions
+/// or because it was deleted while we were scanning.
+#[derive(Debug)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub source: io::Error,
+}
+
+impl ScanError {
+    pub fn new(path: &Path, source: io::Error) -> Self {
+        ScanError {
+            path: path.to_owned(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "cannot read '{}': {}", self.path.display(), self.source)
+    }
+}
+
+impl std::error::Error for ScanError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
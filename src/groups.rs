@@ -0,0 +1,141 @@
+/**
+* Adding up the whole tree by what kind of file things are rather than by where they are
+* (--by-ext and --by-type).
+*
+* Every worker adds the files it reads to its own groups, which are merged once the walk is done,
+* like the largest files for --top. Only the largest file of each group is remembered, so memory
+* use depends on the number of groups rather than on the number of files.
+*/
+use std::{cmp::Ordering, collections::HashMap, ffi::OsStr, path::PathBuf};
+
+use crate::{args::SizeKind, scan::Size, sort};
+
+/// The files that fell into one group.
+#[derive(Debug, PartialEq)]
+pub struct Group {
+    pub name: String,
+    pub size: Size,
+    pub files: u64,
+    /// The largest file in the group, with its size.
+    pub largest: Option<(PathBuf, Size)>,
+}
+
+impl Group {
+    /// Keep a file as the largest one if it is. Of files of the same size, the path that sorts
+    /// first wins, so that which one is shown doesn't depend on the order they were found in.
+    fn offer_largest(&mut self, size_kind: SizeKind, size: Size, path: impl FnOnce() -> PathBuf) {
+        let Some((largest_path, largest)) = &self.largest else {
+            self.largest = Some((path(), size));
+            return;
+        };
+        match size.get(size_kind).cmp(&largest.get(size_kind)) {
+            Ordering::Greater => self.largest = Some((path(), size)),
+            Ordering::Equal => {
+                let path = path();
+                if path < *largest_path {
+                    self.largest = Some((path, size));
+                }
+            }
+            Ordering::Less => {}
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Groups {
+    /// Which size picks the largest file.
+    size_kind: SizeKind,
+    groups: HashMap<String, Group>,
+}
+
+impl Groups {
+    pub fn new(size_kind: SizeKind) -> Self {
+        Groups {
+            size_kind,
+            groups: HashMap::new(),
+        }
+    }
+
+    /// Add a file to a group, only building its path if it might be the largest one so far.
+    pub fn add(&mut self, key: &str, size: Size, path: impl FnOnce() -> PathBuf) {
+        if !self.groups.contains_key(key) {
+            let group = Group {
+                name: key.to_owned(),
+                size: Size::default(),
+                files: 0,
+                largest: None,
+            };
+            self.groups.insert(key.to_owned(), group);
+        }
+        let group = self.groups.get_mut(key).expect("the group was just added");
+        group.size.add(size);
+        group.files += 1;
+        group.offer_largest(self.size_kind, size, path);
+    }
+
+    pub fn merge(&mut self, other: Groups) {
+        for (key, other) in other.groups {
+            let Some(group) = self.groups.get_mut(&key) else {
+                self.groups.insert(key, other);
+                continue;
+            };
+            group.size.add(other.size);
+            group.files += other.files;
+            if let Some((path, size)) = other.largest {
+                group.offer_largest(self.size_kind, size, || path);
+            }
+        }
+    }
+
+    /// The groups, in no particular order.
+    pub fn into_groups(self) -> Vec<Group> {
+        self.groups.into_values().collect()
+    }
+}
+
+/// The group for a file with --by-ext, with extensions that only differ in case counted together.
+pub fn extension_key(name: &OsStr) -> String {
+    match sort::extension(name) {
+        ext if ext.is_empty() => "(no extension)".to_owned(),
+        ext => format!(".{}", ext),
+    }
+}
+
+#[test]
+fn files_are_grouped_by_extension() {
+    let size = |bytes| Size {
+        apparent: bytes,
+        disk: bytes,
+    };
+    let mut first = Groups::new(SizeKind::Apparent);
+    let mut second = Groups::new(SizeKind::Apparent);
+    for (i, (name, bytes)) in [("a.rs", 10), ("b.RS", 30), ("Makefile", 5), ("c.rs", 20)]
+        .into_iter()
+        .enumerate()
+    {
+        let groups = if i % 2 == 0 { &mut first } else { &mut second };
+        let key = extension_key(OsStr::new(name));
+        groups.add(&key, size(bytes), || PathBuf::from(name));
+    }
+    first.merge(second);
+
+    let mut groups = first.into_groups();
+    groups.sort_by(|a, b| a.name.cmp(&b.name));
+    assert_eq!(
+        groups,
+        [
+            Group {
+                name: "(no extension)".to_owned(),
+                size: size(5),
+                files: 1,
+                largest: Some((PathBuf::from("Makefile"), size(5))),
+            },
+            Group {
+                name: ".rs".to_owned(),
+                size: size(60),
+                files: 3,
+                largest: Some((PathBuf::from("b.RS"), size(30))),
+            },
+        ]
+    );
+}
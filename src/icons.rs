@@ -0,0 +1,115 @@
+/**
+* Icons shown in front of every entry.
+*
+* These are Nerd Font glyphs (https://www.nerdfonts.com), picked by the kind of entry and its
+* extension. Terminals without a patched font show them as boxes, and there's no way to tell
+* whether one is installed, so unless they're asked for with --icons=always we use the classic
+* `ls -F` markers instead: `/` for folders, `@` for symlinks, `*` for executables and so on.
+*/
+use std::{ffi::OsStr, path::Path};
+
+use crate::{args::When, EntryKind};
+
+/// Whether to use Nerd Font glyphs for the icons. A UTF-8 terminal can still be missing the font,
+/// so `auto` means the markers, which look right everywhere.
+pub fn use_nerd_fonts(when: When) -> bool {
+    when == When::Always
+}
+
+pub fn icon(name: &OsStr, kind: EntryKind, executable: bool, nerd_fonts: bool) -> String {
+    if !nerd_fonts {
+        return match kind {
+            EntryKind::File if executable => "*",
+            EntryKind::File => " ",
+            kind => kind.marker(),
+        }
+        .to_owned();
+    }
+    let icon = match kind {
+        EntryKind::File => file_icon(name, executable),
+        EntryKind::Directory => '\u{f07b}',   // nf-fa-folder
+        EntryKind::Symlink => '\u{f0c1}',     // nf-fa-link
+        EntryKind::Fifo => '\u{f07e5}',       // nf-md-pipe
+        EntryKind::Socket => '\u{f1e6}',      // nf-fa-plug
+        EntryKind::BlockDevice => '\u{f0a0}', // nf-fa-hdd_o
+        EntryKind::CharDevice => '\u{f11c}',  // nf-fa-keyboard_o
+    };
+    icon.to_string()
+}
+
+fn file_icon(name: &OsStr, executable: bool) -> char {
+    let name = name.to_string_lossy().to_lowercase();
+    match name.as_str() {
+        "dockerfile" => return '\u{f308}', // nf-linux-docker
+        "makefile" | "cmakelists.txt" => return '\u{e779}', // nf-dev-gnu
+        "license" | "licence" | "copying" => return '\u{f0219}', // nf-md-license
+        ".gitignore" | ".gitattributes" | ".gitmodules" => return '\u{f1d3}', // nf-fa-git
+        _ => {}
+    }
+
+    let extension = Path::new(&name)
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match extension.as_str() {
+        // Archives and compressed files
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "zst" | "7z" | "rar" | "lz4" | "deb"
+        | "rpm" | "jar" | "whl" => '\u{f410}', // nf-oct-file_zip
+        // Images
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "svg" | "webp" | "ico" | "tiff" | "heic" => {
+            '\u{f1c5}' // nf-fa-file_image_o
+        }
+        "mp3" | "flac" | "wav" | "ogg" | "m4a" | "opus" => '\u{f001}', // nf-fa-music
+        "mp4" | "mkv" | "avi" | "mov" | "webm" => '\u{f03d}',          // nf-fa-video_camera
+        "pdf" => '\u{f1c1}',                                           // nf-fa-file_pdf_o
+        // Source code
+        "rs" => '\u{e7a8}',                           // nf-dev-rust
+        "py" => '\u{e606}',                           // nf-seti-python
+        "js" | "mjs" | "cjs" => '\u{e74e}',           // nf-dev-javascript
+        "ts" | "tsx" => '\u{e628}',                   // nf-seti-typescript
+        "go" => '\u{e626}',                           // nf-seti-go
+        "c" | "h" => '\u{e61e}',                      // nf-custom-c
+        "cpp" | "cc" | "cxx" | "hpp" => '\u{e61d}',   // nf-custom-cpp
+        "java" | "class" => '\u{e738}',               // nf-dev-java
+        "rb" => '\u{e739}',                           // nf-dev-ruby
+        "sh" | "bash" | "zsh" | "fish" => '\u{f489}', // nf-oct-terminal
+        "html" | "htm" => '\u{f13b}',                 // nf-fa-html5
+        "css" | "scss" => '\u{e749}',                 // nf-dev-css3
+        "kt" | "swift" | "lua" | "php" | "cs" | "hs" | "ml" | "ex" | "zig" | "sql" => {
+            '\u{f121}' // nf-fa-code
+        }
+        // Data and configuration
+        "json" => '\u{e60b}',                                   // nf-seti-json
+        "toml" | "yaml" | "yml" | "ini" | "conf" => '\u{e615}', // nf-seti-config
+        "md" | "markdown" => '\u{f48a}',                        // nf-oct-markdown
+        "txt" | "log" => '\u{f15c}',                            // nf-fa-file_text
+        _ if executable => '\u{f489}',                          // nf-oct-terminal
+        _ => '\u{f15b}',                                        // nf-fa-file
+    }
+}
+
+#[test]
+fn icons_by_extension() {
+    assert_eq!(file_icon(OsStr::new("main.rs"), false), '\u{e7a8}');
+    assert_eq!(file_icon(OsStr::new("Backup.TAR.GZ"), false), '\u{f410}');
+    assert_eq!(file_icon(OsStr::new("photo.jpeg"), false), '\u{f1c5}');
+    assert_eq!(file_icon(OsStr::new("Makefile"), false), '\u{e779}');
+    assert_eq!(file_icon(OsStr::new("run"), true), '\u{f489}');
+    assert_eq!(file_icon(OsStr::new("notes"), false), '\u{f15b}');
+}
+
+#[test]
+fn glyphs_only_when_asked_for() {
+    assert!(use_nerd_fonts(When::Always));
+    assert!(!use_nerd_fonts(When::Auto));
+    assert!(!use_nerd_fonts(When::Never));
+}
+
+#[test]
+fn ascii_fallback() {
+    let tmp = OsStr::new("tmp");
+    assert_eq!(icon(tmp, EntryKind::Directory, false, false), "/");
+    assert_eq!(icon(tmp, EntryKind::Directory, false, true), "\u{f07b}");
+    assert_eq!(icon(tmp, EntryKind::File, true, false), "*");
+    assert_eq!(icon(tmp, EntryKind::Fifo, false, false), "|");
+}
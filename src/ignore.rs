@@ -0,0 +1,256 @@
+/**
+* Skipping paths during the scan, with --exclude patterns and ignore files.
+*
+* Patterns work like the ones in .gitignore: a pattern without a slash matches the name of an
+* entry anywhere below the folder it applies to, a pattern with a slash matches the path relative
+* to that folder, a trailing slash only matches folders, and `!` in front of a pattern in an ignore
+* file brings back something an earlier pattern skipped. `*` and `?` don't match a slash, `**`
+* matches any number of folders, and `[...]` matches one character out of a set.
+*
+* Patterns given on the command line apply to every PATH and always win. Ignore files apply to the
+* folder they're in and everything below it, with the ones deeper down taking precedence.
+*/
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Always read, so that a project can keep things like build outputs out of pdu's results.
+pub const PDU_IGNORE_FILE: &str = ".pduignore";
+
+/// Only read with --gitignore.
+pub const GIT_IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+#[derive(Debug)]
+pub struct Pattern {
+    glob: Vec<char>,
+    negated: bool,
+    dir_only: bool,
+    /// Whether the pattern is matched against the whole relative path rather than just the name.
+    anchored: bool,
+}
+
+impl Pattern {
+    /// Parse a line of an ignore file, which might be blank or a comment.
+    pub fn parse(line: &str) -> Option<Pattern> {
+        let mut line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let negated = line.starts_with('!');
+        // A backslash lets a pattern start with a literal ! or #.
+        if negated || line.starts_with("\\!") || line.starts_with("\\#") {
+            line = &line[1..];
+        }
+        let dir_only = line.ends_with('/');
+        let line = line.trim_end_matches('/');
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(Pattern {
+            glob: line.chars().collect(),
+            negated,
+            dir_only,
+            anchored,
+        })
+    }
+
+    /// Whether the pattern matches a path relative to the folder the pattern applies to.
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let text = if self.anchored {
+            relative
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative)
+        };
+        glob_match(&self.glob, &text.chars().collect::<Vec<_>>())
+    }
+}
+
+pub fn parse_lines(text: &str) -> Vec<Pattern> {
+    text.lines().filter_map(Pattern::parse).collect()
+}
+
+/// Whether the last of `patterns` to match `path` skips it, if any of them match. The patterns
+/// apply to everything below `base`.
+fn decide(patterns: &[Pattern], base: &Path, path: &Path, is_dir: bool) -> Option<bool> {
+    // Checked first, since there usually aren't any patterns and this runs for every entry.
+    if patterns.is_empty() {
+        return None;
+    }
+    let relative = path.strip_prefix(base).ok()?;
+    let relative = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    patterns
+        .iter()
+        .rev()
+        .find(|p| p.matches(&relative, is_dir))
+        .map(|p| !p.negated)
+}
+
+pub fn excluded_by(patterns: &[Pattern], base: &Path, path: &Path, is_dir: bool) -> bool {
+    decide(patterns, base, path, is_dir).unwrap_or(false)
+}
+
+/// The ignore files that apply to a folder: the ones in it, and then those of its parents.
+#[derive(Debug)]
+pub struct Ignore {
+    dir: PathBuf,
+    patterns: Vec<Pattern>,
+    parent: Option<Arc<Ignore>>,
+}
+
+impl Ignore {
+    pub fn new(dir: PathBuf, patterns: Vec<Pattern>, parent: Option<Arc<Ignore>>) -> Arc<Ignore> {
+        Arc::new(Ignore {
+            dir,
+            patterns,
+            parent,
+        })
+    }
+
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        let mut ignore = Some(self);
+        while let Some(current) = ignore {
+            if let Some(excluded) = decide(&current.patterns, &current.dir, path, is_dir) {
+                return excluded;
+            }
+            ignore = current.parent.as_deref();
+        }
+        false
+    }
+}
+
+/// Match a glob against some text, where neither `*` nor `?` match a slash.
+fn glob_match(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        ['*', '*', rest @ ..] => {
+            // `**/` also matches no folders at all.
+            if let ['/', after @ ..] = rest {
+                if glob_match(after, text) {
+                    return true;
+                }
+            }
+            (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        ['*', rest @ ..] => {
+            for i in 0..=text.len() {
+                if glob_match(rest, &text[i..]) {
+                    return true;
+                }
+                if text.get(i) == Some(&'/') {
+                    break;
+                }
+            }
+            false
+        }
+        ['?', rest @ ..] => match text {
+            [c, text @ ..] if *c != '/' => glob_match(rest, text),
+            _ => false,
+        },
+        ['[', class @ ..] => match (char_class(class), text) {
+            (Some((matches, len)), [c, text @ ..]) => {
+                matches(*c) && glob_match(&class[len..], text)
+            }
+            (Some(_), []) => false,
+            // Without a closing bracket it's just a bracket.
+            (None, [c, text @ ..]) => *c == '[' && glob_match(class, text),
+            (None, []) => false,
+        },
+        ['\\', c, rest @ ..] | [c, rest @ ..] => match text {
+            [t, text @ ..] if t == c => glob_match(rest, text),
+            _ => false,
+        },
+    }
+}
+
+/// Parse a character class like `[a-z]` or `[!0-9]` from just after its opening bracket,
+/// returning a matcher for it and how many characters it took up.
+fn char_class(glob: &[char]) -> Option<(impl Fn(char) -> bool + '_, usize)> {
+    let negated = matches!(glob.first(), Some('!') | Some('^'));
+    let start = usize::from(negated);
+    // A closing bracket right at the start is part of the set.
+    let end = start + 1 + glob.get(start + 1..)?.iter().position(|&c| c == ']')?;
+    let set = &glob[start..end];
+    let matches = move |c: char| {
+        let mut found = false;
+        let mut i = 0;
+        while i < set.len() {
+            if i + 2 < set.len() && set[i + 1] == '-' {
+                found |= (set[i]..=set[i + 2]).contains(&c);
+                i += 3;
+            } else {
+                found |= set[i] == c;
+                i += 1;
+            }
+        }
+        found != negated
+    };
+    Some((matches, end + 1))
+}
+
+#[cfg(test)]
+fn glob(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    glob_match(&pattern, &text)
+}
+
+#[test]
+fn globs() {
+    assert!(glob("*.log", "debug.log"));
+    assert!(!glob("*.log", "logs/debug.log"));
+    assert!(glob("debug?.log", "debug1.log"));
+    assert!(!glob("debug?.log", "debug.log"));
+    assert!(glob("**/build", "build"));
+    assert!(glob("**/build", "a/b/build"));
+    assert!(glob("docs/**", "docs/a/b.md"));
+    assert!(glob("a/**/b", "a/b"));
+    assert!(glob("a/**/b", "a/x/y/b"));
+    assert!(glob("[abc].txt", "b.txt"));
+    assert!(!glob("[!abc].txt", "b.txt"));
+    assert!(glob("file[0-9]", "file7"));
+    assert!(glob("[]]", "]"));
+    assert!(glob("a[", "a["));
+    assert!(glob("\\*", "*"));
+    assert!(!glob("\\*", "x"));
+}
+
+#[test]
+fn ignore_file_rules() {
+    let root = Path::new("root");
+    let patterns = parse_lines("# comment\n\n*.o\n!keep.o\ntarget/\n/top\nsrc/gen\n");
+    let excluded = |path: &str, is_dir| excluded_by(&patterns, root, &root.join(path), is_dir);
+
+    assert!(excluded("a.o", false));
+    assert!(excluded("deep/down/a.o", false));
+    assert!(!excluded("keep.o", false));
+    assert!(excluded("target", true));
+    assert!(!excluded("target", false));
+    assert!(excluded("top", false));
+    assert!(!excluded("sub/top", false));
+    assert!(excluded("src/gen", true));
+    assert!(!excluded("other/src/gen", true));
+}
+
+#[test]
+fn deeper_ignore_files_win() {
+    let parent = Ignore::new(PathBuf::from("root"), parse_lines("*.log"), None);
+    let child = Ignore::new(
+        PathBuf::from("root/logs"),
+        parse_lines("!keep.log"),
+        Some(parent),
+    );
+
+    assert!(child.is_excluded(Path::new("root/logs/other.log"), false));
+    assert!(!child.is_excluded(Path::new("root/logs/keep.log"), false));
+    assert!(!child.is_excluded(Path::new("root/logs/notes.txt"), false));
+}
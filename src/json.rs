@@ -0,0 +1,313 @@
+/**
+* JSON output, for scripts and dashboards. The schema is described in the README, and anything
+* added to it later will only ever be new fields, so consumers should ignore fields they don't
+* know about.
+*
+* With --json everything is written as a single document. With --json-lines every entry is
+* written on a line of its own as soon as it's serialized, so that huge results can be processed
+* a line at a time without holding the whole document in memory.
+*
+* Paths that aren't valid UTF-8 are written with the invalid bytes replaced by U+FFFD, since a JSON
+* string can't hold anything else.
+*/
+use std::{
+    io::{self, Write},
+    path::Path,
+};
+
+use crate::{dupes::DuplicateSet, groups::Group, EntryKind, Listing, PathData};
+
+/// Bumped whenever a field changes meaning or goes away.
+const SCHEMA_VERSION: u32 = 1;
+
+pub fn write_json(listings: &[Listing], tree: bool, out: &mut impl Write) -> io::Result<()> {
+    write!(out, "{{\"version\":{},\"roots\":[", SCHEMA_VERSION)?;
+    for (i, listing) in listings.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{{\"path\":")?;
+        write_string(out, &listing.root.to_string_lossy())?;
+        write!(
+            out,
+            ",\"type\":\"{}\",\"entries\":[",
+            kind_name(listing.kind)
+        )?;
+        for (j, d) in listing.entries.iter().enumerate() {
+            if j > 0 {
+                write!(out, ",")?;
+            }
+            write_entry(out, &listing.root, d, tree)?;
+        }
+        write!(out, "],\"total\":")?;
+        write_total(out, &listing.total)?;
+        write!(out, "}}")?;
+    }
+    write!(out, "],\"total\":")?;
+    write_total(out, &grand_total(listings))?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+pub fn write_json_lines(listings: &[Listing], out: &mut impl Write) -> io::Result<()> {
+    for listing in listings {
+        for d in &listing.entries {
+            write_entry_lines(out, &listing.root, &listing.root, d, 1)?;
+        }
+        write!(out, "{{\"record\":\"total\",\"root\":")?;
+        write_string(out, &listing.root.to_string_lossy())?;
+        write!(out, ",")?;
+        write_total_fields(out, &listing.total)?;
+        writeln!(out, "}}")?;
+    }
+    write!(out, "{{\"record\":\"grand_total\",")?;
+    write_total_fields(out, &grand_total(listings))?;
+    writeln!(out, "}}")?;
+    out.flush()
+}
+
+/// The sets of identical files found by `pdu dupes`.
+pub fn write_duplicates(sets: &[DuplicateSet], out: &mut impl Write) -> io::Result<()> {
+    write!(out, "{{\"version\":{},\"duplicates\":[", SCHEMA_VERSION)?;
+    for (i, set) in sets.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{{")?;
+        write_duplicate_fields(out, set)?;
+        write!(out, "}}")?;
+    }
+    writeln!(out, "]}}")?;
+    out.flush()
+}
+
+pub fn write_duplicates_lines(sets: &[DuplicateSet], out: &mut impl Write) -> io::Result<()> {
+    for set in sets {
+        write!(out, "{{\"record\":\"duplicates\",")?;
+        write_duplicate_fields(out, set)?;
+        writeln!(out, "}}")?;
+    }
+    out.flush()
+}
+
+fn write_duplicate_fields(out: &mut impl Write, set: &DuplicateSet) -> io::Result<()> {
+    let reclaimable = set.reclaimable();
+    write!(
+        out,
+        "\"size\":{},\"disk_size\":{},\"reclaimable\":{},\"reclaimable_disk\":{},\"paths\":[",
+        set.size.apparent, set.size.disk, reclaimable.apparent, reclaimable.disk
+    )?;
+    for (i, path) in set.paths.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write_string(out, &path.to_string_lossy())?;
+    }
+    write!(out, "]")
+}
+
+/// What the files add up to with --by-ext.
+pub fn write_groups(groups: &[Group], out: &mut impl Write) -> io::Result<()> {
+    write!(out, "{{\"version\":{},\"groups\":[", SCHEMA_VERSION)?;
+    for (i, group) in groups.iter().enumerate() {
+        if i > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "{{")?;
+        write_group_fields(out, group)?;
+        write!(out, "}}")?;
+    }
+    writeln!(out, "]}}")?;
+    out.flush()
+}
+
+pub fn write_groups_lines(groups: &[Group], out: &mut impl Write) -> io::Result<()> {
+    for group in groups {
+        write!(out, "{{\"record\":\"group\",")?;
+        write_group_fields(out, group)?;
+        writeln!(out, "}}")?;
+    }
+    out.flush()
+}
+
+fn write_group_fields(out: &mut impl Write, group: &Group) -> io::Result<()> {
+    write!(out, "\"name\":")?;
+    write_string(out, &group.name)?;
+    write!(
+        out,
+        ",\"size\":{},\"disk_size\":{},\"files\":{}",
+        group.size.apparent, group.size.disk, group.files
+    )?;
+    if let Some((path, size)) = &group.largest {
+        write!(out, ",\"largest\":{{\"path\":")?;
+        write_string(out, &path.to_string_lossy())?;
+        write!(
+            out,
+            ",\"size\":{},\"disk_size\":{}}}",
+            size.apparent, size.disk
+        )?;
+    }
+    Ok(())
+}
+
+fn write_entry(out: &mut impl Write, parent: &Path, d: &PathData, tree: bool) -> io::Result<()> {
+    let path = parent.join(&d.name);
+    write!(out, "{{")?;
+    write_entry_fields(out, &path, d)?;
+    if tree && d.kind == EntryKind::Directory {
+        write!(out, ",\"children\":[")?;
+        for (i, child) in d.children.iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write_entry(out, &path, child, tree)?;
+        }
+        write!(out, "]")?;
+    }
+    write!(out, "}}")
+}
+
+fn write_entry_lines(
+    out: &mut impl Write,
+    root: &Path,
+    parent: &Path,
+    d: &PathData,
+    depth: usize,
+) -> io::Result<()> {
+    let path = parent.join(&d.name);
+    write!(out, "{{\"record\":\"entry\",\"root\":")?;
+    write_string(out, &root.to_string_lossy())?;
+    write!(out, ",\"depth\":{},", depth)?;
+    write_entry_fields(out, &path, d)?;
+    writeln!(out, "}}")?;
+    for child in &d.children {
+        write_entry_lines(out, root, &path, child, depth + 1)?;
+    }
+    Ok(())
+}
+
+fn write_entry_fields(out: &mut impl Write, path: &Path, d: &PathData) -> io::Result<()> {
+    write!(out, "\"path\":")?;
+    write_string(out, &path.to_string_lossy())?;
+    write!(out, ",\"name\":")?;
+    write_string(out, &d.name.to_string_lossy())?;
+    write!(out, ",\"type\":\"{}\",", kind_name(d.kind))?;
+    if d.mount_point {
+        write!(out, "\"mount_point\":true,")?;
+    }
+    write_total_fields(out, d)
+}
+
+fn write_total(out: &mut impl Write, total: &PathData) -> io::Result<()> {
+    write!(out, "{{")?;
+    write_total_fields(out, total)?;
+    write!(out, "}}")
+}
+
+fn write_total_fields(out: &mut impl Write, d: &PathData) -> io::Result<()> {
+    write!(
+        out,
+        "\"size\":{},\"disk_size\":{},\"files\":{},\"dirs\":{}",
+        d.size.apparent, d.size.disk, d.files, d.dirs
+    )
+}
+
+fn grand_total(listings: &[Listing]) -> PathData {
+    let mut total = crate::total_row(Default::default(), 0, 0);
+    for listing in listings {
+        total.size.add(listing.total.size);
+        total.files += listing.total.files;
+        total.dirs += listing.total.dirs;
+    }
+    total
+}
+
+fn kind_name(kind: EntryKind) -> &'static str {
+    match kind {
+        EntryKind::File => "file",
+        EntryKind::Directory => "directory",
+        EntryKind::Symlink => "symlink",
+        EntryKind::Fifo => "fifo",
+        EntryKind::Socket => "socket",
+        EntryKind::BlockDevice => "block_device",
+        EntryKind::CharDevice => "char_device",
+    }
+}
+
+fn write_string(out: &mut impl Write, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            '\n' => write!(out, "\\n")?,
+            '\r' => write!(out, "\\r")?,
+            '\t' => write!(out, "\\t")?,
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32)?,
+            c => write!(out, "{}", c)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+#[cfg(test)]
+fn sample_listing() -> Listing {
+    use crate::scan::Size;
+    use std::{ffi::OsString, path::PathBuf};
+
+    let size = Size {
+        apparent: 10,
+        disk: 4096,
+    };
+    let mut dir = PathData::new(OsString::from("a\"b"), EntryKind::Directory, size);
+    dir.files = 1;
+    dir.children
+        .push(PathData::new(OsString::from("c"), EntryKind::File, size));
+    Listing {
+        root: PathBuf::from("root"),
+        kind: EntryKind::Directory,
+        icon: " ".to_owned(),
+        entries: vec![dir],
+        largest: vec![],
+        total: crate::total_row(size, 1, 1),
+    }
+}
+
+#[test]
+fn json_document() {
+    let mut out = vec![];
+    write_json(&[sample_listing()], true, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        concat!(
+            r#"{"version":1,"roots":[{"path":"root","type":"directory","entries":["#,
+            r#"{"path":"root/a\"b","name":"a\"b","type":"directory","size":10,"disk_size":4096,"files":1,"dirs":0,"children":["#,
+            r#"{"path":"root/a\"b/c","name":"c","type":"file","size":10,"disk_size":4096,"files":1,"dirs":0}]}],"#,
+            r#""total":{"size":10,"disk_size":4096,"files":1,"dirs":1}}],"#,
+            r#""total":{"size":10,"disk_size":4096,"files":1,"dirs":1}}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn json_lines() {
+    let mut out = vec![];
+    write_json_lines(&[sample_listing()], &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with(r#"{"record":"entry","root":"root","depth":1,"path":"root/a\"b""#));
+    assert!(
+        lines[1].starts_with(r#"{"record":"entry","root":"root","depth":2,"path":"root/a\"b/c""#)
+    );
+    assert!(lines[2].starts_with(r#"{"record":"total","root":"root","size":10"#));
+    assert!(lines[3].starts_with(r#"{"record":"grand_total","size":10"#));
+}
+
+#[test]
+fn control_characters_are_escaped() {
+    let mut out = vec![];
+    write_string(&mut out, "tab\there\u{1}\\").unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), r#""tab\there\u0001\\""#);
+}
@@ -0,0 +1,96 @@
+/**
+* Telling what's in a file from its first few bytes (--by-type), for when extensions are missing
+* or wrong, like for build artifacts and cache blobs.
+*
+* Only the first SNIFF_SIZE bytes of each file are read, which is enough for every format we know
+* and keeps the scan fast however big the files are. Anything that isn't one of the formats below
+* is text if those bytes are UTF-8 without any NUL bytes, and data otherwise.
+*/
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+/// How much of each file is read. A tar header's magic is the furthest in, at offset 257.
+const SNIFF_SIZE: u64 = 512;
+
+/// What's in a file, given the magic bytes it starts with, in the order they're tried.
+const SIGNATURES: &[(&str, usize, &[u8])] = &[
+    ("ELF", 0, b"\x7fELF"),
+    ("Mach-O", 0, b"\xcf\xfa\xed\xfe"),
+    ("Mach-O", 0, b"\xce\xfa\xed\xfe"),
+    ("PE executable", 0, b"MZ"),
+    ("gzip", 0, b"\x1f\x8b"),
+    ("zstd", 0, b"\x28\xb5\x2f\xfd"),
+    ("xz", 0, b"\xfd7zXZ\x00"),
+    ("bzip2", 0, b"BZh"),
+    ("zip", 0, b"PK\x03\x04"),
+    ("7z", 0, b"7z\xbc\xaf\x27\x1c"),
+    ("tar", 257, b"ustar"),
+    ("PNG", 0, b"\x89PNG\r\n\x1a\n"),
+    ("JPEG", 0, b"\xff\xd8\xff"),
+    ("GIF", 0, b"GIF8"),
+    ("MP4", 4, b"ftyp"),
+    ("SQLite", 0, b"SQLite format 3\x00"),
+    ("PDF", 0, b"%PDF-"),
+    ("Parquet", 0, b"PAR1"),
+];
+
+/// Read the start of a file and tell what's in it.
+pub fn sniff(path: &Path) -> io::Result<&'static str> {
+    let mut start = Vec::with_capacity(SNIFF_SIZE as usize);
+    File::open(path)?.take(SNIFF_SIZE).read_to_end(&mut start)?;
+    Ok(classify(&start))
+}
+
+pub fn classify(start: &[u8]) -> &'static str {
+    if start.is_empty() {
+        return "empty";
+    }
+    let signature = SIGNATURES.iter().find(|(_, offset, magic)| {
+        start
+            .get(*offset..offset + magic.len())
+            .is_some_and(|bytes| bytes == *magic)
+    });
+    if let Some((name, _, _)) = signature {
+        return name;
+    }
+    if is_text(start) {
+        "text"
+    } else {
+        "data"
+    }
+}
+
+fn is_text(start: &[u8]) -> bool {
+    if start.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(start) {
+        Ok(_) => true,
+        // A character cut in half at the end of what was read doesn't count.
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+#[test]
+fn files_are_told_apart_by_their_start() {
+    assert_eq!(classify(b"\x7fELF\x02\x01\x01"), "ELF");
+    assert_eq!(classify(b"\x1f\x8b\x08\x00"), "gzip");
+    assert_eq!(classify(b"\x28\xb5\x2f\xfd\x04"), "zstd");
+    assert_eq!(classify(b"\x89PNG\r\n\x1a\n\x00\x00"), "PNG");
+    assert_eq!(classify(b"\xff\xd8\xff\xe0"), "JPEG");
+    assert_eq!(classify(b"SQLite format 3\x00\x10\x00"), "SQLite");
+    assert_eq!(classify(b"%PDF-1.7\n"), "PDF");
+    let mut tar = vec![0u8; 512];
+    tar[..4].copy_from_slice(b"file");
+    tar[257..262].copy_from_slice(b"ustar");
+    assert_eq!(classify(&tar), "tar");
+
+    assert_eq!(classify(b""), "empty");
+    assert_eq!(classify("fn main() {}\n// caf\u{e9}".as_bytes()), "text");
+    assert_eq!(classify(&"\u{e9}".as_bytes()[..1]), "text");
+    assert_eq!(classify(b"\x00\x01\x02"), "data");
+    assert_eq!(classify(b"\xff\xfe\xfd"), "data");
+}
@@ -5,132 +5,999 @@
 *
 * Note on folder sizes:
 * On some operating systems (at least on unix-based ones), folders themselves have a size of 4kb.
-* This program does not count these so-called folder-sizes, only file-sizes.
+* The apparent size (--apparent-size) does not count these so-called folder-sizes, only
+* file-sizes. The default disk usage does count them, since those blocks really are taken up on
+* disk, which keeps the numbers in line with what `du` reports.
+*
+* Note on disk usage:
+* By default sizes are the space actually allocated on disk (st_blocks * 512), which can be much
+* smaller than the apparent size for sparse files, or larger for small files that still take up
+* a whole block.
 *
 * Note on large filesizes:
-* It is assumed that the total size of a single file or directory in bytes will not exceed the
-* limit set by the u64 type. This means that the ZiB and YiB suffixes are probably impossible to
-* see.
+* Sizes are kept in a u64, and sums saturate at its limit (16 EiB) instead of overflowing. This
+* means that the ZiB and YiB suffixes are probably impossible to see.
+*
+* Note on errors:
+* Anything that can't be read during the scan (missing permissions, files deleted while we're
+* looking at them, ...) is skipped and reported on stderr once the listing is printed, and pdu
+* exits with a non-zero status since the sizes shown are incomplete.
 */
-use std::{env::current_dir, ffi::OsString, path::PathBuf};
+mod args;
+mod color;
+mod dupes;
+mod error;
+mod groups;
+mod icons;
+mod ignore;
+mod json;
+mod magic;
+mod queue;
+mod scan;
+mod size;
+mod sort;
+mod top;
+mod tui;
+mod users;
+
+use std::{
+    cmp::Ordering,
+    ffi::{OsStr, OsString},
+    io::{self, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::SystemTime,
+};
 
+use args::{Command, Format, GroupBy, Options, Parsed, SizeKind, SortKey};
+use color::Colors;
+use dupes::DuplicateSet;
+use error::ScanError;
+use groups::Group;
+use scan::{HardLinks, Scanner, Size};
+use size::SizeFormat;
 use term_grid::{Cell, Direction, Filling, Grid, GridOptions};
-use walkdir::WalkDir;
+use users::Names;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+    File,
+    Directory,
+    /// A symlink that wasn't followed, see --follow.
+    Symlink,
+    /// A named pipe.
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl EntryKind {
+    /// The marker `ls -F` puts after names of this kind, with # and % for devices, which `ls`
+    /// doesn't mark.
+    fn marker(self) -> &'static str {
+        match self {
+            EntryKind::File => "",
+            EntryKind::Directory => "/",
+            EntryKind::Symlink => "@",
+            EntryKind::Fifo => "|",
+            EntryKind::Socket => "=",
+            EntryKind::BlockDevice => "#",
+            EntryKind::CharDevice => "%",
+        }
+    }
+}
 
 #[derive(Debug)]
 struct PathData {
-    size: u64,
+    size: Size,
     name: OsString,
     icon: String,
+    kind: EntryKind,
+    executable: bool,
+    /// When the entry itself was last modified, if the platform keeps track of that.
+    modified: Option<SystemTime>,
+    /// When the most recently modified file in it was modified, or when the entry was if it's not
+    /// a folder.
+    newest: Option<SystemTime>,
+    /// The ids of the user and the group that own it, if the platform has them.
+    owner: Option<u32>,
+    group: Option<u32>,
+    /// Whether it's a folder another filesystem is mounted on, which wasn't read because of
+    /// --one-file-system.
+    mount_point: bool,
+    /// How many files there are in it, or 1 for a file.
+    files: u64,
+    /// How many folders there are in it, at any depth.
+    dirs: u64,
+    /// What's inside a folder, if it's no deeper than --max-depth.
+    children: Vec<PathData>,
 }
 
 impl PathData {
-    fn get_human_readable_size(&self) -> String {
-        let mut out = self.size as f64;
-        let mut suffix = "YiB";
-        for unit in vec!["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB"] {
-            if out < 1024.0 {
-                suffix = unit;
-                break;
-            }
-            out /= 1024.0;
+    fn new(name: OsString, kind: EntryKind, size: Size) -> Self {
+        PathData {
+            size,
+            name,
+            icon: " ".to_owned(),
+            kind,
+            executable: false,
+            modified: None,
+            newest: None,
+            owner: None,
+            group: None,
+            mount_point: false,
+            files: if kind == EntryKind::File { 1 } else { 0 },
+            dirs: 0,
+            children: vec![],
         }
-        return format!("{:.3} {}", out, suffix);
+    }
+
+    /// The name to show, with a note if the entry wasn't read.
+    fn display_name(&self) -> String {
+        let name = self.name.to_string_lossy();
+        if self.mount_point {
+            format!("{} [mount point]", name)
+        } else {
+            name.into_owned()
+        }
+    }
+
+    fn get_human_readable_size(&self, kind: SizeKind, format: SizeFormat) -> String {
+        format.format(self.size.get(kind))
     }
 }
 
-fn main() -> Result<(), std::io::Error> {
-    let cwd = current_dir()?;
-    let mut data = get_data_from_directory(cwd)?;
-    print_data(&mut data);
-    Ok(())
+/// Everything found under one of the paths given on the command line.
+#[derive(Debug)]
+struct Listing {
+    root: PathBuf,
+    kind: EntryKind,
+    /// The icon for the root itself, for when it's shown as an entry of a merged listing.
+    icon: String,
+    entries: Vec<PathData>,
+    /// The largest files anywhere under the root, largest first, with --top.
+    largest: Vec<PathData>,
+    total: PathData,
 }
 
-fn get_data_from_directory(dir: PathBuf) -> Result<Vec<PathData>, std::io::Error> {
-    let mut data: Vec<PathData> = vec![];
-    let mut total_size: u64 = 0;
+/// How many unreadable entries to list individually before just counting them.
+const MAX_REPORTED_ERRORS: usize = 20;
 
-    for file in dir.read_dir()?.filter_map(|f| f.ok()) {
-        if file.metadata()?.is_dir() {
-            let size = get_size_of_directory(file.path());
-            total_size += size;
-            data.push(PathData {
-                size,
-                name: file.file_name(),
-                icon: " ".to_owned(),
-            })
-        } else if file.metadata()?.is_file() {
-            let size = file.metadata()?.len();
-            total_size += size;
-            data.push(PathData {
-                size,
-                name: file.file_name(),
-                icon: " ".to_owned(),
+fn main() -> ExitCode {
+    let options = match args::parse(std::env::args_os().skip(1)) {
+        Ok(Parsed::Run(options)) => *options,
+        Ok(Parsed::Help) => {
+            print!("{}", args::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Ok(Parsed::Version) => {
+            println!("pdu {}", env!("CARGO_PKG_VERSION"));
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("pdu: {}", e);
+            eprintln!("Try 'pdu --help' for more information.");
+            return ExitCode::from(2);
+        }
+    };
+
+    if options.interactive && !io::stdout().is_terminal() {
+        eprintln!("pdu: --interactive needs a terminal");
+        return ExitCode::from(2);
+    }
+
+    let mut listings = vec![];
+    let mut errors = vec![];
+    let mut scanner = Scanner::new(&options);
+    for path in &options.paths {
+        match scanner.get_data_from_path(path) {
+            Ok(listing) => listings.push(listing),
+            Err(e) => errors.push(e),
+        }
+    }
+    if options.command == Command::Dupes {
+        let sets = scanner.duplicates();
+        errors.append(&mut scanner.errors);
+        let written = match options.format {
+            Format::Text => {
+                print_duplicates(&sets, &options, &mut BufWriter::new(io::stdout().lock()))
+            }
+            Format::Json => json::write_duplicates(&sets, &mut BufWriter::new(io::stdout().lock())),
+            Format::JsonLines => {
+                json::write_duplicates_lines(&sets, &mut BufWriter::new(io::stdout().lock()))
+            }
+        };
+        return finish(written, &errors);
+    }
+    if let Some(group_by) = options.group_by {
+        let mut groups = scanner.groups();
+        errors.append(&mut scanner.errors);
+        sort_groups(&mut groups, &options);
+        let written = match options.format {
+            Format::Text => {
+                let mut out = BufWriter::new(io::stdout().lock());
+                print_groups(&groups, group_by, &options, &mut out)
+            }
+            Format::Json => json::write_groups(&groups, &mut BufWriter::new(io::stdout().lock())),
+            Format::JsonLines => {
+                json::write_groups_lines(&groups, &mut BufWriter::new(io::stdout().lock()))
+            }
+        };
+        return finish(written, &errors);
+    }
+    errors.append(&mut scanner.errors);
+    if let Some(n) = options.top.filter(|_| !options.interactive) {
+        listings = largest_files(listings, n, &options);
+    }
+
+    let written = match options.format {
+        _ if options.interactive => browse(listings, &options),
+        Format::Text => {
+            let mut out = BufWriter::new(io::stdout().lock());
+            print_listings(listings, &options, &mut out).and_then(|()| {
+                if options.show_deduplicated {
+                    print_hard_links(&scanner.skipped_links, &options, &mut out)
+                } else {
+                    Ok(())
+                }
             })
         }
+        Format::Json => {
+            let tree = options.max_depth.is_some();
+            json::write_json(&listings, tree, &mut BufWriter::new(io::stdout().lock()))
+        }
+        Format::JsonLines => {
+            json::write_json_lines(&listings, &mut BufWriter::new(io::stdout().lock()))
+        }
+    };
+    finish(written, &errors)
+}
+
+/// Report any errors from writing the output or from the scan, and pick the exit code.
+fn finish(written: io::Result<()>, errors: &[ScanError]) -> ExitCode {
+    match written {
+        // Whatever was reading the output, like `head`, has seen all it wanted to.
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => {}
+        Err(e) => {
+            eprintln!("pdu: cannot write output: {}", e);
+            return ExitCode::FAILURE;
+        }
+        Ok(()) => {}
     }
 
-    data.push(PathData {
-        size: total_size,
-        name: OsString::from("Total"),
+    if errors.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    report_errors(errors);
+    ExitCode::FAILURE
+}
+
+fn browse(mut listings: Vec<Listing>, options: &Options) -> io::Result<()> {
+    let listing = if listings.len() == 1 {
+        listings.remove(0)
+    } else {
+        merge_listings(listings)
+    };
+    let root = PathData {
+        files: listing.total.files,
+        dirs: listing.total.dirs,
+        children: listing.entries,
+        ..PathData::new(
+            listing.root.into_os_string(),
+            EntryKind::Directory,
+            listing.total.size,
+        )
+    };
+    tui::run(root, options.size_kind, options.size_format)
+}
+
+fn print_listings(
+    listings: Vec<Listing>,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let colors = Colors::new(options);
+    let names = if options.show_owner || options.show_group {
+        Names::load()
+    } else {
+        Names::default()
+    };
+    // With --top, the listings were already merged.
+    if options.merge && options.top.is_none() {
+        print_data(merge_listings(listings), options, &colors, &names, out)?;
+        return out.flush();
+    }
+
+    let show_headers = listings.len() > 1;
+    for listing in listings {
+        if show_headers {
+            writeln!(out, "{}:", listing.root.display())?;
+        }
+        print_data(listing, options, &colors, &names, out)?;
+    }
+    out.flush()
+}
+
+/// List every set of identical files, the ones with the most to gain last so they're the easiest
+/// to find.
+fn print_duplicates(
+    sets: &[DuplicateSet],
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let format = |size: Size| options.size_format.format(size.get(options.size_kind));
+    let mut total = Size::default();
+    for set in sets {
+        let reclaimable = set.reclaimable();
+        total.add(reclaimable);
+        writeln!(
+            out,
+            "{} copies of {}, {} reclaimable:",
+            set.paths.len(),
+            format(set.size),
+            format(reclaimable)
+        )?;
+        for path in &set.paths {
+            writeln!(out, "  {}", path.display())?;
+        }
+    }
+    writeln!(
+        out,
+        "Duplicates: {} {}, {} reclaimable",
+        sets.len(),
+        if sets.len() == 1 { "set" } else { "sets" },
+        format(total)
+    )?;
+    out.flush()
+}
+
+/// Sort groups by size, name or number of files, and then by name.
+fn sort_groups(groups: &mut [Group], options: &Options) {
+    groups.sort_by(|a, b| {
+        let kind = options.size_kind;
+        let order = match options.sort {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Count => a.files.cmp(&b.files),
+            // Nothing else can be asked for with --by-ext and the like.
+            _ => a.size.get(kind).cmp(&b.size.get(kind)),
+        }
+        .then_with(|| sort::compare_names(OsStr::new(&a.name), OsStr::new(&b.name)));
+        if options.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+}
+
+/// List what each group adds up to, with its largest file.
+fn print_groups(
+    groups: &[Group],
+    group_by: GroupBy,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let colors = Colors::new(options);
+    let mut grid = Grid::new(GridOptions {
+        filling: Filling::Spaces(1),
+        direction: Direction::LeftToRight,
+    });
+    let kinds: &[SizeKind] = if options.both_sizes {
+        &[SizeKind::Disk, SizeKind::Apparent]
+    } else {
+        &[options.size_kind]
+    };
+
+    let mut headers = vec![match group_by {
+        GroupBy::Extension => "Extension",
+        GroupBy::ContentType => "Type",
+        GroupBy::Owner => "Owner",
+        GroupBy::Group => "Group",
+    }];
+    if options.both_sizes {
+        headers.extend(["Disk", "Apparent"]);
+    } else {
+        headers.push("Size");
+    }
+    if options.percent {
+        headers.push("%");
+    }
+    headers.extend(["Files", "Largest file"]);
+    let columns = headers.len();
+    for header in headers {
+        grid.add(Cell::from(header));
+    }
+
+    let mut total = Size::default();
+    let mut files = 0;
+    for group in groups {
+        total.add(group.size);
+        files += group.files;
+    }
+    let add_row = |grid: &mut Grid, name: Cell, size: Size, files: u64, largest: String| {
+        grid.add(name);
+        for &kind in kinds {
+            let style = colors.size(size.get(kind), total.get(kind));
+            grid.add(color::cell(
+                options.size_format.format(size.get(kind)),
+                style,
+            ));
+        }
+        if options.percent {
+            let share =
+                size.get(options.size_kind) as f64 / total.get(options.size_kind).max(1) as f64;
+            grid.add(Cell::from(format!("{:.1}%", share * 100.0)));
+        }
+        grid.add(Cell::from(size::group_thousands(files)));
+        grid.add(Cell::from(largest));
+    };
+    let add_total = |grid: &mut Grid| {
+        let name = color::cell("Total".to_owned(), colors.total());
+        add_row(grid, name, total, files, String::new());
+    };
+
+    let total_first = sort::total_first(options);
+    if total_first {
+        add_total(&mut grid);
+    }
+    for group in groups {
+        let largest = match &group.largest {
+            Some((path, size)) => format!(
+                "{} ({})",
+                path.display(),
+                options.size_format.format(size.get(options.size_kind))
+            ),
+            None => String::new(),
+        };
+        add_row(
+            &mut grid,
+            Cell::from(group.name.as_str()),
+            group.size,
+            group.files,
+            largest,
+        );
+    }
+    if !total_first {
+        add_total(&mut grid);
+    }
+    write!(out, "{}", grid.fit_into_columns(columns))?;
+    out.flush()
+}
+
+fn report_errors(errors: &[ScanError]) {
+    for e in errors.iter().take(MAX_REPORTED_ERRORS) {
+        eprintln!("pdu: {}", e);
+    }
+    if errors.len() > MAX_REPORTED_ERRORS {
+        eprintln!("pdu: ... and {} more", errors.len() - MAX_REPORTED_ERRORS);
+    }
+    eprintln!(
+        "pdu: {} {} could not be read, sizes are incomplete",
+        errors.len(),
+        if errors.len() == 1 {
+            "entry"
+        } else {
+            "entries"
+        }
+    );
+}
+
+fn print_hard_links(
+    skipped: &HardLinks,
+    options: &Options,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let size = PathData::new(OsString::new(), EntryKind::File, skipped.size);
+    writeln!(
+        out,
+        "Hard links: {} extra {} to already counted files, {} not counted twice",
+        skipped.links,
+        if skipped.links == 1 { "link" } else { "links" },
+        size.get_human_readable_size(options.size_kind, options.size_format)
+    )?;
+    out.flush()
+}
+
+fn total_row(size: Size, files: u64, dirs: u64) -> PathData {
+    PathData {
         icon: "".to_string(),
+        files,
+        dirs,
+        ..PathData::new(OsString::from("Total"), EntryKind::Directory, size)
+    }
+}
+
+/// Replace the entries of each listing with the largest files under its root, or with the
+/// largest files under all of them when merging.
+fn largest_files(listings: Vec<Listing>, n: usize, options: &Options) -> Vec<Listing> {
+    let listings = listings.into_iter().map(|listing| {
+        // A file given on the command line is the only file there is.
+        let entries = match listing.kind {
+            EntryKind::Directory => listing.largest,
+            _ => listing.entries,
+        };
+        Listing {
+            entries,
+            largest: vec![],
+            ..listing
+        }
+    });
+    if !options.merge {
+        return listings.collect();
+    }
+
+    let mut merged = merge_listings(vec![]);
+    let mut largest = top::TopFiles::new(n);
+    for listing in listings {
+        merged.total.size.add(listing.total.size);
+        merged.total.files += listing.total.files;
+        merged.total.dirs += listing.total.dirs;
+        merged.total.newest = merged.total.newest.max(listing.total.newest);
+        for entry in listing.entries {
+            let name = match listing.kind {
+                EntryKind::Directory => listing.root.join(&entry.name).into_os_string(),
+                _ => entry.name.clone(),
+            };
+            let rank = entry.size.get(options.size_kind);
+            largest.push(rank, PathData { name, ..entry });
+        }
+    }
+    merged.entries = largest.into_entries();
+    vec![merged]
+}
+
+/// Combine several listings into one with a row per root and a grand total.
+fn merge_listings(listings: Vec<Listing>) -> Listing {
+    let mut entries = vec![];
+    let mut total_size = Size::default();
+    let mut total_files = 0;
+    let mut total_dirs = 0;
+    let mut newest = None;
+    for listing in listings {
+        total_size.add(listing.total.size);
+        total_files += listing.total.files;
+        total_dirs += listing.total.dirs;
+        newest = newest.max(listing.total.newest);
+        entries.push(PathData {
+            icon: listing.icon,
+            files: listing.total.files,
+            dirs: listing.total.dirs,
+            newest: listing.total.newest,
+            children: listing.entries,
+            ..PathData::new(
+                listing.root.into_os_string(),
+                listing.kind,
+                listing.total.size,
+            )
+        });
+    }
+
+    Listing {
+        root: PathBuf::new(),
+        kind: EntryKind::Directory,
+        icon: " ".to_owned(),
+        entries,
+        largest: vec![],
+        total: PathData {
+            newest,
+            ..total_row(total_size, total_files, total_dirs)
+        },
+    }
+}
+
+fn print_data(
+    mut listing: Listing,
+    options: &Options,
+    colors: &Colors,
+    names: &Names,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let tree = options.max_depth.is_some();
+    if options.min_size > 0 || options.limit.is_some() {
+        fold_small_entries(&mut listing.entries, options);
+    }
+    sort_entries(&mut listing.entries, tree, options);
+
+    // The bars take up whatever room is left once everything else has been laid out.
+    let bar_width = options.bars.then(|| {
+        let (grid, columns) = build_grid(&listing, options, colors, names, None);
+        let used = grid.fit_into_columns(columns).width();
+        match tui::terminal_width() {
+            Some(width) => width
+                .saturating_sub(used + 1)
+                .clamp(MIN_BAR_WIDTH, MAX_BAR_WIDTH),
+            None => DEFAULT_BAR_WIDTH,
+        }
+    });
+    let (grid, columns) = build_grid(&listing, options, colors, names, bar_width);
+    writeln!(out, "{}", grid.fit_into_columns(columns))
+}
+
+/// The smallest and largest bars to draw, and the width to use when we can't tell how wide the
+/// terminal is.
+const MIN_BAR_WIDTH: usize = 10;
+const MAX_BAR_WIDTH: usize = 60;
+const DEFAULT_BAR_WIDTH: usize = 20;
+
+/// Replace the entries smaller than --min-size, and all but the --limit largest, with a single
+/// "(N others)" entry adding them up, in every folder of the tree.
+fn fold_small_entries(entries: &mut Vec<PathData>, options: &Options) {
+    for d in entries.iter_mut() {
+        fold_small_entries(&mut d.children, options);
+    }
+
+    let kind = options.size_kind;
+    entries.sort_by(|a, b| {
+        b.size
+            .get(kind)
+            .cmp(&a.size.get(kind))
+            .then_with(|| sort::compare_names(&a.name, &b.name))
     });
+    let keep = entries
+        .iter()
+        .take(options.limit.unwrap_or(usize::MAX))
+        .take_while(|d| d.size.get(kind) >= options.min_size)
+        .count();
+    let others = entries.split_off(keep);
+    if others.is_empty() {
+        return;
+    }
+
+    let name = format!(
+        "({} {})",
+        others.len(),
+        if others.len() == 1 { "other" } else { "others" }
+    );
+    let mut other = PathData::new(OsString::from(name), EntryKind::File, Size::default());
+    other.files = 0;
+    for d in others {
+        other.size.add(d.size);
+        other.files += d.files;
+        other.dirs += d.dirs + u64::from(d.kind == EntryKind::Directory);
+        other.newest = other.newest.max(d.newest);
+    }
+    entries.push(other);
+}
+
+fn sort_entries(data: &mut [PathData], tree: bool, options: &Options) {
+    sort::sort(data, options);
+    if tree {
+        for d in data {
+            sort_entries(&mut d.children, tree, options);
+        }
+    }
+}
 
-    return Ok(data);
+/// What's needed to draw the rows besides the entries themselves.
+struct Row<'a> {
+    options: &'a Options,
+    colors: &'a Colors,
+    names: &'a Names,
+    total: &'a PathData,
+    /// The size of the largest entry, which gets the longest bar.
+    largest: u64,
+    bar_width: Option<usize>,
 }
 
-fn print_data(data: &mut Vec<PathData>) {
+/// Lay out the listing in a grid, returning it along with the number of columns.
+fn build_grid(
+    listing: &Listing,
+    options: &Options,
+    colors: &Colors,
+    names: &Names,
+    bar_width: Option<usize>,
+) -> (Grid, usize) {
     let mut grid = Grid::new(GridOptions {
         filling: Filling::Spaces(1),
         direction: Direction::LeftToRight,
     });
 
-    data.sort_by_key(|k| k.size);
+    let tree = options.max_depth.is_some();
+    let mut headers = vec!["Name"];
+    if options.both_sizes {
+        headers.extend(["Disk", "Apparent"]);
+    } else {
+        headers.push("Size");
+    }
+    if options.percent {
+        headers.push("%");
+    }
+    if options.counts {
+        headers.extend(["Files", "Dirs"]);
+    }
+    if options.newest {
+        headers.push("Newest");
+    }
+    if options.show_owner {
+        headers.push("Owner");
+    }
+    if options.show_group {
+        headers.push("Group");
+    }
+    if bar_width.is_some() {
+        headers.push("");
+    }
+    let columns = headers.len() + usize::from(!tree);
+    // Without a header there'd be no telling which number is which.
+    if headers.len() > 2 {
+        if !tree {
+            grid.add(Cell::from(""));
+        }
+        for header in headers {
+            grid.add(Cell::from(header));
+        }
+    }
 
-    for d in data {
-        grid.add(Cell::from(d.icon.clone()));
-        grid.add(Cell::from(d.name.to_str().unwrap_or("???")));
-        grid.add(Cell::from(d.get_human_readable_size()))
+    let row = Row {
+        options,
+        colors,
+        names,
+        total: &listing.total,
+        largest: listing
+            .entries
+            .iter()
+            .map(|d| d.size.get(options.size_kind))
+            .max()
+            .unwrap_or(0),
+        bar_width,
+    };
+    let total_first = sort::total_first(options);
+    if total_first {
+        add_total_row(&mut grid, &row);
     }
+    if tree {
+        add_tree_rows(&mut grid, &listing.entries, "", &row);
+    } else {
+        for d in &listing.entries {
+            let style = colors.name(d);
+            grid.add(color::cell(d.icon.clone(), style));
+            grid.add(color::cell(d.display_name(), style));
+            add_number_cells(&mut grid, d, &row);
+        }
+    }
+    if !total_first {
+        add_total_row(&mut grid, &row);
+    }
+    (grid, columns)
+}
+
+/// Add a row for each entry, drawing the tree's branches in front of their names.
+fn add_tree_rows(grid: &mut Grid, data: &[PathData], prefix: &str, row: &Row) {
+    let last = data.len().saturating_sub(1);
+    for (i, d) in data.iter().enumerate() {
+        let (branch, indent) = if i == last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let icon = if d.icon.trim().is_empty() {
+            String::new()
+        } else {
+            format!("{} ", d.icon)
+        };
+        let name = format!("{}{}", icon, d.display_name());
+        let mut cell = color::cell(name, row.colors.name(d));
+        // Only the name is colored, not the branches.
+        let branches = format!("{}{}", prefix, branch);
+        cell.width += Cell::from(branches.as_str()).width;
+        cell.contents.insert_str(0, &branches);
+        grid.add(cell);
+        add_number_cells(grid, d, row);
+
+        let prefix = format!("{}{}", prefix, indent);
+        add_tree_rows(grid, &d.children, &prefix, row);
+    }
+}
 
-    println!("{}", grid.fit_into_columns(3));
+fn add_total_row(grid: &mut Grid, row: &Row) {
+    if row.options.max_depth.is_none() {
+        grid.add(Cell::from(row.total.icon.clone()));
+    }
+    let name = row.total.name.to_string_lossy().into_owned();
+    grid.add(color::cell(name, row.colors.total()));
+    add_number_cells(grid, row.total, row);
 }
 
-fn get_size_of_directory(root: PathBuf) -> u64 {
-    return match WalkDir::new(root)
-        .into_iter()
-        .filter_map(|f| f.ok())
-        .filter_map(|f| f.metadata().ok())
-        // Folders technically take up 4kb of space, but we only care about file sizes
-        .filter(|m| m.is_file())
-        .map(|m| m.len())
-        // Do this instead of .sum() to handle overflow errors
-        .try_fold(0u64, |acc, v| acc.checked_add(v))
-    {
-        // TODO: come up with better error handling?
-        None => panic!("Overflow occurred, get a smaller directory :("),
-        Some(v) => v,
+/// Add the size columns for an entry, colored by how big it is compared to the total, and
+/// whichever of the percentage, counts, newest time, owners and bar are shown.
+fn add_number_cells(grid: &mut Grid, d: &PathData, row: &Row) {
+    let options = row.options;
+    let kinds: &[SizeKind] = if options.both_sizes {
+        &[SizeKind::Disk, SizeKind::Apparent]
+    } else {
+        &[options.size_kind]
     };
+    // The total would always be the biggest, so there's no point in coloring it.
+    let is_total = std::ptr::eq(d, row.total);
+    for &kind in kinds {
+        let style = if is_total {
+            None
+        } else {
+            row.colors.size(d.size.get(kind), row.total.size.get(kind))
+        };
+        grid.add(color::cell(
+            d.get_human_readable_size(kind, options.size_format),
+            style,
+        ));
+    }
+
+    let size = d.size.get(options.size_kind);
+    if options.percent {
+        let share = size as f64 / row.total.size.get(options.size_kind).max(1) as f64;
+        grid.add(Cell::from(format!("{:.1}%", share * 100.0)));
+    }
+    if options.counts {
+        grid.add(Cell::from(size::group_thousands(d.files)));
+        grid.add(Cell::from(size::group_thousands(d.dirs)));
+    }
+    if options.newest {
+        grid.add(Cell::from(d.newest.map_or("-".to_owned(), format_time)));
+    }
+    // Nothing owns the Total, while other entries might just not have owners on this platform.
+    let unknown = || {
+        if is_total {
+            String::new()
+        } else {
+            "-".to_owned()
+        }
+    };
+    if options.show_owner {
+        let owner = d.owner.map(|uid| row.names.user(uid));
+        grid.add(Cell::from(owner.unwrap_or_else(unknown)));
+    }
+    if options.show_group {
+        let group = d.group.map(|gid| row.names.group(gid));
+        grid.add(Cell::from(group.unwrap_or_else(unknown)));
+    }
+    if let Some(width) = row.bar_width {
+        let bar = if is_total {
+            String::new()
+        } else {
+            bar(size, row.largest, width)
+        };
+        grid.add(Cell::from(bar));
+    }
+}
+
+/// A bar `width` characters long for the largest value, using eighths of a block for precision.
+fn bar(value: u64, largest: u64, width: usize) -> String {
+    const EIGHTHS: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+    let eighths = (value as f64 / largest.max(1) as f64 * (width * 8) as f64).round() as usize;
+    let mut bar = "█".repeat(eighths / 8);
+    if !eighths.is_multiple_of(8) {
+        bar.push(EIGHTHS[eighths % 8]);
+    }
+    bar
+}
+
+/// A time as a UTC date and time down to the minute, like 2024-03-09 17:45.
+fn format_time(time: SystemTime) -> String {
+    let seconds = match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(since) => since.as_secs(),
+        Err(_) => 0,
+    };
+    // From days since the epoch to a date, see http://howardhinnant.github.io/date_algorithms.html
+    let days = seconds / 86400 + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60
+    )
 }
 
 #[test]
 fn low_file_sizes_should_have_byte_prefix() {
-    let path = PathData {
-        size: 1000,
-        name: OsString::from("test"),
-        icon: "".to_string(),
+    let size = Size {
+        apparent: 1000,
+        disk: 4096,
     };
-    let human_readable_size = path.get_human_readable_size();
+    let path = PathData::new(OsString::from("test"), EntryKind::File, size);
+    let human_readable_size =
+        path.get_human_readable_size(SizeKind::Apparent, SizeFormat::default());
     assert_eq!(human_readable_size, "1000.000 B");
 }
 
 #[test]
 fn kilobyte_file_size() {
-    let path = PathData {
-        size: 1024,
-        name: OsString::from("test"),
-        icon: "".to_string(),
+    let size = Size {
+        apparent: 1024,
+        disk: 4096,
     };
-    let human_readable_size = path.get_human_readable_size();
+    let path = PathData::new(OsString::from("test"), EntryKind::File, size);
+    let human_readable_size =
+        path.get_human_readable_size(SizeKind::Apparent, SizeFormat::default());
     assert_eq!(human_readable_size, "1.000 KiB");
 }
+
+#[test]
+fn times_are_shown_in_utc() {
+    use std::time::Duration;
+
+    let at = |seconds| format_time(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds));
+    assert_eq!(at(0), "1970-01-01 00:00");
+    assert_eq!(at(951_782_400), "2000-02-29 00:00");
+    assert_eq!(at(1_710_006_300), "2024-03-09 17:45");
+}
+
+#[test]
+fn small_entries_are_folded() {
+    let entry = |name: &str, size| {
+        let size = Size {
+            apparent: size,
+            disk: size,
+        };
+        PathData::new(OsString::from(name), EntryKind::File, size)
+    };
+    let mut entries = vec![entry("a", 5), entry("b", 50), entry("c", 1), entry("d", 20)];
+    let options = Options {
+        min_size: 10,
+        limit: Some(1),
+        ..Options::default()
+    };
+    fold_small_entries(&mut entries, &options);
+
+    let rows: Vec<_> = entries
+        .iter()
+        .map(|d| (d.name.clone(), d.size.disk, d.files))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (OsString::from("b"), 50, 1),
+            (OsString::from("(3 others)"), 26, 3)
+        ]
+    );
+}
+
+#[test]
+fn bars_are_scaled_to_the_largest_entry() {
+    assert_eq!(bar(100, 100, 4), "████");
+    assert_eq!(bar(50, 100, 4), "██");
+    assert_eq!(bar(1, 8, 1), "▏");
+    assert_eq!(bar(45, 100, 2), "▉");
+    assert_eq!(bar(0, 100, 4), "");
+    assert_eq!(bar(0, 0, 4), "");
+}
+
+#[test]
+fn groups_are_sorted_by_the_sort_key() {
+    let group = |name: &str, bytes, files| Group {
+        name: name.to_owned(),
+        size: Size {
+            apparent: bytes,
+            disk: bytes,
+        },
+        files,
+        largest: None,
+    };
+    let sorted = |sort, reverse| {
+        let mut groups = vec![group(".b", 30, 1), group(".c", 10, 3), group(".a", 20, 2)];
+        let options = Options {
+            sort,
+            reverse,
+            ..Options::default()
+        };
+        sort_groups(&mut groups, &options);
+        groups.into_iter().map(|g| g.name).collect::<Vec<_>>()
+    };
+    assert_eq!(sorted(SortKey::Size, false), [".c", ".a", ".b"]);
+    assert_eq!(sorted(SortKey::Name, false), [".a", ".b", ".c"]);
+    assert_eq!(sorted(SortKey::Count, true), [".c", ".a", ".b"]);
+}
@@ -0,0 +1,116 @@
+use std::{
+    collections::VecDeque,
+    sync::{Condvar, Mutex},
+};
+
+/// A work-stealing queue shared by a fixed number of workers.
+///
+/// Each worker pushes and pops jobs at the back of its own deque, so it works depth-first through
+/// whatever it found itself. When that runs dry it steals from the front of the other workers'
+/// deques, which is where the biggest, least recently discovered jobs are. When there's nothing
+/// to steal either, it sleeps until a job is pushed or the last one is finished.
+pub struct WorkQueue<T> {
+    deques: Vec<Mutex<VecDeque<T>>>,
+    state: Mutex<State>,
+    /// Signalled whenever a job is pushed, and once every job has been finished.
+    changed: Condvar,
+}
+
+struct State {
+    /// Jobs that have been pushed but not finished yet.
+    pending: usize,
+    /// How many jobs have ever been pushed, so that a worker can tell whether one was pushed
+    /// since it last looked at the deques.
+    pushed: u64,
+}
+
+impl<T> WorkQueue<T> {
+    pub fn new(workers: usize) -> Self {
+        WorkQueue {
+            deques: (0..workers.max(1))
+                .map(|_| Mutex::new(VecDeque::new()))
+                .collect(),
+            state: Mutex::new(State {
+                pending: 0,
+                pushed: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    pub fn push(&self, worker: usize, job: T) {
+        // Counted before anyone can take it, so that it can't be finished before it's pending.
+        self.state.lock().unwrap().pending += 1;
+        self.deques[worker].lock().unwrap().push_back(job);
+        self.state.lock().unwrap().pushed += 1;
+        self.changed.notify_one();
+    }
+
+    /// Get the next job for `worker`, waiting for other workers to share theirs if needed. Returns
+    /// `None` once every job has been finished, since nothing more can be pushed after that.
+    ///
+    /// Every job returned has to be handed back to `finish` once it's done.
+    pub fn pop(&self, worker: usize) -> Option<T> {
+        loop {
+            let pushed = self.state.lock().unwrap().pushed;
+            if let Some(job) = self.deques[worker].lock().unwrap().pop_back() {
+                return Some(job);
+            }
+            let others = (1..self.deques.len()).map(|i| (worker + i) % self.deques.len());
+            for other in others {
+                if let Some(job) = self.deques[other].lock().unwrap().pop_front() {
+                    return Some(job);
+                }
+            }
+
+            // Someone is still busy with a job and might push more.
+            let mut state = self.state.lock().unwrap();
+            while state.pending > 0 && state.pushed == pushed {
+                state = self.changed.wait(state).unwrap();
+            }
+            if state.pending == 0 {
+                return None;
+            }
+        }
+    }
+
+    /// Mark a job as done. Any jobs it pushed must have been pushed before this.
+    pub fn finish(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.pending -= 1;
+        if state.pending == 0 {
+            self.changed.notify_all();
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.deques.len()
+    }
+}
+
+#[test]
+fn every_job_is_run_once() {
+    // Each job n spawns jobs 2n and 2n + 1, like walking a binary tree.
+    let queue = WorkQueue::new(4);
+    let seen = Mutex::new(vec![]);
+    queue.push(0, 1u32);
+    std::thread::scope(|s| {
+        for worker in 0..queue.workers() {
+            let (queue, seen) = (&queue, &seen);
+            s.spawn(move || {
+                while let Some(n) = queue.pop(worker) {
+                    if n < 512 {
+                        queue.push(worker, 2 * n);
+                        queue.push(worker, 2 * n + 1);
+                    }
+                    seen.lock().unwrap().push(n);
+                    queue.finish();
+                }
+            });
+        }
+    });
+
+    let mut seen = seen.into_inner().unwrap();
+    seen.sort();
+    assert_eq!(seen, (1..1024).collect::<Vec<_>>());
+}
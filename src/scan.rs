@@ -0,0 +1,1414 @@
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs::{self, DirEntry, Metadata, ReadDir},
+    io, mem,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::SystemTime,
+};
+
+use crate::{
+    args::{Command, Follow, GroupBy, Options, SizeKind},
+    dupes::{self, DuplicateSet, FileInfo},
+    error::ScanError,
+    groups::{self, Group, Groups},
+    icons,
+    ignore::{self, Ignore, Pattern},
+    magic,
+    queue::WorkQueue,
+    top::TopFiles,
+    total_row,
+    users::Names,
+    EntryKind, Listing, PathData,
+};
+
+/// The size of a file, or of everything inside a folder, in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Size {
+    /// The number of bytes you could read out of it.
+    pub apparent: u64,
+    /// The number of bytes allocated for it on disk.
+    pub disk: u64,
+}
+
+impl Size {
+    pub fn of(metadata: &Metadata) -> Size {
+        Size {
+            apparent: if metadata.is_dir() { 0 } else { metadata.len() },
+            disk: disk_size(metadata),
+        }
+    }
+
+    pub fn add(&mut self, other: Size) {
+        self.apparent = self.apparent.saturating_add(other.apparent);
+        self.disk = self.disk.saturating_add(other.disk);
+    }
+
+    pub fn get(&self, kind: SizeKind) -> u64 {
+        match kind {
+            SizeKind::Apparent => self.apparent,
+            SizeKind::Disk => self.disk,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn disk_size(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+    // st_blocks is always in 512-byte units, whatever the filesystem's block size is.
+    metadata.blocks().saturating_mul(512)
+}
+
+#[cfg(not(unix))]
+fn disk_size(metadata: &Metadata) -> u64 {
+    if metadata.is_dir() {
+        0
+    } else {
+        metadata.len()
+    }
+}
+
+#[cfg(unix)]
+pub fn is_executable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(_metadata: &Metadata) -> bool {
+    false
+}
+
+#[cfg(unix)]
+fn device_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+/// Identifies a file or folder, however it was reached.
+#[cfg(unix)]
+fn inode(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// The ids of the user and the group that own an entry.
+#[cfg(unix)]
+fn ownership(metadata: &Metadata) -> (Option<u32>, Option<u32>) {
+    use std::os::unix::fs::MetadataExt;
+    (Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn ownership(_metadata: &Metadata) -> (Option<u32>, Option<u32>) {
+    (None, None)
+}
+
+/// Identifies a file with more than one hard link to it, so that we only count it once.
+#[cfg(unix)]
+fn hard_link_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    if metadata.is_dir() || metadata.nlink() <= 1 {
+        return None;
+    }
+    inode(metadata)
+}
+
+#[cfg(not(unix))]
+fn hard_link_id(_metadata: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(unix)]
+fn entry_kind(metadata: &Metadata) -> EntryKind {
+    use std::os::unix::fs::FileTypeExt;
+    let file_type = metadata.file_type();
+    if file_type.is_dir() {
+        EntryKind::Directory
+    } else if file_type.is_symlink() {
+        EntryKind::Symlink
+    } else if file_type.is_fifo() {
+        EntryKind::Fifo
+    } else if file_type.is_socket() {
+        EntryKind::Socket
+    } else if file_type.is_block_device() {
+        EntryKind::BlockDevice
+    } else if file_type.is_char_device() {
+        EntryKind::CharDevice
+    } else {
+        EntryKind::File
+    }
+}
+
+#[cfg(not(unix))]
+fn entry_kind(metadata: &Metadata) -> EntryKind {
+    if metadata.is_dir() {
+        EntryKind::Directory
+    } else if metadata.is_symlink() {
+        EntryKind::Symlink
+    } else {
+        EntryKind::File
+    }
+}
+
+/// Which files to count, by when they were last modified, or accessed with --atime.
+#[derive(Debug, Default)]
+struct AgeFilter {
+    /// Only files from before this time are counted.
+    before: Option<SystemTime>,
+    /// Only files from after this time are counted.
+    after: Option<SystemTime>,
+    atime: bool,
+}
+
+impl AgeFilter {
+    fn new(options: &Options) -> Self {
+        let now = SystemTime::now();
+        // An age reaching back before the epoch lets everything through.
+        let ago = |age| now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH);
+        AgeFilter {
+            before: options.older_than.map(ago),
+            after: options.newer_than.map(ago),
+            atime: options.atime,
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.before.is_some() || self.after.is_some()
+    }
+
+    fn matches(&self, metadata: &Metadata) -> bool {
+        if !self.is_active() {
+            return true;
+        }
+        let time = if self.atime {
+            metadata.accessed()
+        } else {
+            metadata.modified()
+        };
+        // Without the time there's no telling how old it is.
+        let Ok(time) = time else {
+            return false;
+        };
+        self.before.is_none_or(|before| time <= before)
+            && self.after.is_none_or(|after| time > after)
+    }
+}
+
+/// The group for files whose owner isn't known, on platforms that don't have owners.
+const UNKNOWN_OWNER: &str = "(unknown)";
+
+/// Extra links to files that were already counted elsewhere in the scan.
+#[derive(Debug, Default)]
+pub struct HardLinks {
+    pub links: u64,
+    pub size: Size,
+}
+
+impl HardLinks {
+    fn add(&mut self, other: &HardLinks) {
+        self.links += other.links;
+        self.size.add(other.size);
+    }
+
+    /// Note down a link that isn't counted, returning what it adds to the total.
+    fn skip(&mut self, size: Size) -> Size {
+        self.links += 1;
+        self.size.add(size);
+        Size::default()
+    }
+}
+
+/// How the size of an entry is counted.
+enum Charge {
+    Now,
+    /// It was already counted through another path.
+    Skip,
+    /// It might be reached through more than one path, so which one it's counted under is decided
+    /// once the walk is done.
+    Later((u64, u64)),
+}
+
+/// Walks the roots given on the command line. Hard links are tracked across the whole scan, so a
+/// file linked from two different roots is only counted in the first one.
+///
+/// Folders are read by a pool of worker threads, with each folder that's found becoming a new job
+/// for the pool. Every folder's results are kept under an id, and since a folder's id is always
+/// handed out after its parent's, the tree can be put back together by adding each folder to its
+/// parent in reverse id order once the walk is done. Files with several hard links are put aside
+/// until then, and counted under the path that sorts first, so which thread read what has no
+/// effect on the results.
+///
+/// With -L, a symlink to a folder inside the root isn't followed, since the folder is counted
+/// under its real path. Symlinks to folders outside the root are followed once everything else has
+/// been read, one at a time in sorted order, so that of several symlinks to the same folder the one
+/// that sorts first is the one that shows what's in it.
+pub struct Scanner {
+    count_links: bool,
+    /// How many levels below the root to keep entries for.
+    max_depth: usize,
+    threads: usize,
+    nerd_fonts: bool,
+    /// --exclude patterns, which apply below every root.
+    exclude: Vec<Pattern>,
+    /// The names of the ignore files to read in every folder.
+    ignore_files: Vec<&'static str>,
+    one_file_system: bool,
+    follow: Follow,
+    /// How many of the largest files to keep track of, with --top.
+    top: usize,
+    size_kind: SizeKind,
+    age: AgeFilter,
+    group_by: Option<GroupBy>,
+    groups: Groups,
+    /// User and group names, with --by-owner and --by-group.
+    names: Names,
+    /// Whether to keep a list of every file, for finding duplicates.
+    collect_files: bool,
+    files: Vec<FileInfo>,
+    /// Folders that have been read, when following symlinks, so that none is read twice and a
+    /// symlink pointing back up the tree doesn't send us round in circles.
+    visited: Mutex<HashSet<(u64, u64)>>,
+    /// Files that have already been counted.
+    seen: Mutex<HashSet<(u64, u64)>>,
+    pub skipped_links: HardLinks,
+    pub errors: Vec<ScanError>,
+}
+
+/// A folder waiting to be read.
+struct DirJob {
+    path: PathBuf,
+    id: usize,
+    parent: usize,
+    /// How many levels below the root the folder is, the root itself being at 0.
+    depth: usize,
+    entry: PathData,
+    /// The ignore files found in the folder's parents.
+    ignore: Option<Arc<Ignore>>,
+}
+
+/// A folder that's been read, with everything directly inside it added up.
+struct Folder {
+    parent: usize,
+    /// Whether the folder should be listed in its parent's children.
+    keep: bool,
+    entry: PathData,
+}
+
+/// A file that's been read but not yet added to its folder.
+struct FileEntry {
+    path: PathBuf,
+    data: PathData,
+    /// Its group, with --by-ext and the like.
+    group: Option<String>,
+    /// Whether its size is added to the total, rather than it being another path to a file
+    /// that's counted elsewhere.
+    counted: bool,
+}
+
+/// A file that might be reached through more than one path, waiting for the walk to be done.
+struct LinkedFile {
+    id: (u64, u64),
+    /// The folder it was found in, and how deep that folder is.
+    folder: usize,
+    depth: usize,
+    file: FileEntry,
+}
+
+/// A symlink to a folder outside the root, waiting to be followed.
+struct OutsideLink {
+    path: PathBuf,
+    name: OsString,
+    /// The folder it was found in, and how deep that folder is.
+    folder: usize,
+    depth: usize,
+    /// The symlink itself, and the folder it points to.
+    link: Metadata,
+    target: Metadata,
+    ignore: Option<Arc<Ignore>>,
+}
+
+/// State shared by all the workers during a walk.
+struct Walk {
+    root: PathBuf,
+    /// The root with every symlink in it resolved, for telling where a symlink leads.
+    real_root: PathBuf,
+    /// The filesystem the root is on, with --one-file-system.
+    device: Option<u64>,
+    queue: WorkQueue<DirJob>,
+    next_id: AtomicUsize,
+}
+
+impl Walk {
+    /// Whether a symlink leads to somewhere inside the root.
+    fn contains(&self, path: &Path) -> bool {
+        // A symlink with no telling where it leads isn't followed.
+        fs::canonicalize(path).map_or(true, |target| target.starts_with(&self.real_root))
+    }
+}
+
+/// What a single worker has found so far.
+#[derive(Default)]
+struct Worker {
+    id: usize,
+    folders: Vec<(usize, Folder)>,
+    skipped_links: HardLinks,
+    errors: Vec<ScanError>,
+    largest: TopFiles,
+    files: Vec<FileInfo>,
+    groups: Groups,
+    linked: Vec<LinkedFile>,
+    outside: Vec<OutsideLink>,
+}
+
+impl Scanner {
+    pub fn new(options: &Options) -> Self {
+        Scanner {
+            count_links: options.count_links,
+            // The interactive browser needs the whole tree.
+            max_depth: if options.interactive {
+                usize::MAX
+            } else if options.command == Command::Dupes || options.group_by.is_some() {
+                // Only the files matter, not the tree.
+                0
+            } else {
+                options.max_depth.unwrap_or(1)
+            },
+            threads: options
+                .threads
+                .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get())),
+            nerd_fonts: icons::use_nerd_fonts(options.icons),
+            exclude: options
+                .exclude
+                .iter()
+                .filter_map(|p| Pattern::parse(p))
+                .collect(),
+            ignore_files: if options.gitignore {
+                vec![
+                    ignore::PDU_IGNORE_FILE,
+                    ignore::GIT_IGNORE_FILES[0],
+                    ignore::GIT_IGNORE_FILES[1],
+                ]
+            } else {
+                vec![ignore::PDU_IGNORE_FILE]
+            },
+            one_file_system: options.one_file_system,
+            follow: options.follow,
+            top: options.top.unwrap_or(0),
+            size_kind: options.size_kind,
+            age: AgeFilter::new(options),
+            group_by: options.group_by,
+            groups: Groups::new(options.size_kind),
+            names: match options.group_by {
+                Some(GroupBy::Owner | GroupBy::Group) => Names::load(),
+                _ => Names::default(),
+            },
+            collect_files: options.command == Command::Dupes,
+            files: vec![],
+            visited: Mutex::new(HashSet::new()),
+            seen: Mutex::new(HashSet::new()),
+            skipped_links: HardLinks::default(),
+            errors: vec![],
+        }
+    }
+
+    pub fn get_data_from_path(&mut self, path: &Path) -> Result<Listing, ScanError> {
+        let metadata = match self.follow {
+            Follow::Never => path.symlink_metadata(),
+            // A broken symlink is counted as itself.
+            Follow::Cli | Follow::Always => path.metadata().or_else(|_| path.symlink_metadata()),
+        }
+        .map_err(|e| ScanError::new(path, e))?;
+        let kind = entry_kind(&metadata);
+        let executable = is_executable(&metadata);
+        let icon = icons::icon(path.as_os_str(), kind, executable, self.nerd_fonts);
+        if metadata.is_dir() {
+            let mut listing = self.get_data_from_directory(path, &metadata)?;
+            listing.icon = icon;
+            return Ok(listing);
+        }
+
+        // A file given directly is reported as a listing with just itself in it.
+        if !self.age.matches(&metadata) {
+            return Ok(Listing {
+                root: path.to_owned(),
+                kind,
+                icon,
+                entries: vec![],
+                largest: vec![],
+                total: total_row(Size::default(), 0, 0),
+            });
+        }
+        let size = match self.charge(&metadata, false) {
+            Charge::Now => Size::of(&metadata),
+            Charge::Later(id) if self.count_once(id) => Size::of(&metadata),
+            Charge::Skip | Charge::Later(_) => self.skipped_links.skip(Size::of(&metadata)),
+        };
+        let modified = metadata.modified().ok();
+        let (owner, group) = ownership(&metadata);
+        if self.collect_files && kind == EntryKind::File {
+            self.files.push(file_info(path.to_owned(), &metadata));
+        }
+        if let Some(group_by) = self.group_by.filter(|_| kind == EntryKind::File) {
+            let key = self
+                .group_key(group_by, path, &metadata)
+                .map_err(|e| ScanError::new(path, e))?;
+            self.groups.add(&key, size, || path.to_owned());
+        }
+        Ok(Listing {
+            root: path.to_owned(),
+            kind,
+            icon: icon.clone(),
+            entries: vec![PathData {
+                icon,
+                executable,
+                modified,
+                newest: modified,
+                owner,
+                group,
+                ..PathData::new(path.as_os_str().to_owned(), kind, size)
+            }],
+            largest: vec![],
+            total: PathData {
+                newest: modified,
+                ..total_row(size, u64::from(kind == EntryKind::File), 0)
+            },
+        })
+    }
+
+    fn get_data_from_directory(
+        &mut self,
+        dir: &Path,
+        metadata: &Metadata,
+    ) -> Result<Listing, ScanError> {
+        let device = device_id(metadata).filter(|_| self.one_file_system);
+        self.walk_directory(dir, metadata, device)
+    }
+
+    /// Read everything under a folder, leaving out whatever isn't on `device` if it's given.
+    fn walk_directory(
+        &mut self,
+        dir: &Path,
+        metadata: &Metadata,
+        device: Option<u64>,
+    ) -> Result<Listing, ScanError> {
+        let entries = dir.read_dir().map_err(|e| ScanError::new(dir, e))?;
+        self.first_visit(metadata);
+        let root = DirJob {
+            path: dir.to_owned(),
+            id: 0,
+            parent: 0,
+            depth: 0,
+            // The root itself isn't counted, so that the total is the sum of the entries.
+            entry: PathData::new(
+                dir.as_os_str().to_owned(),
+                EntryKind::Directory,
+                Size::default(),
+            ),
+            ignore: None,
+        };
+
+        let walk = Walk {
+            root: dir.to_owned(),
+            real_root: fs::canonicalize(dir).unwrap_or_else(|_| dir.to_owned()),
+            device,
+            queue: WorkQueue::new(self.threads),
+            next_id: AtomicUsize::new(1),
+        };
+        let mut workers: Vec<Worker> = (0..walk.queue.workers())
+            .map(|id| Worker {
+                id,
+                largest: TopFiles::new(self.top),
+                groups: Groups::new(self.size_kind),
+                ..Worker::default()
+            })
+            .collect();
+
+        let mut folders: Vec<Option<Folder>> = vec![];
+        let mut largest = TopFiles::new(self.top);
+        let mut groups = Groups::new(self.size_kind);
+        let mut outside = vec![];
+        self.get_size_of_directory(root, Ok(entries), &walk, &mut workers[0]);
+        loop {
+            self.run(&walk, &mut workers);
+            folders.resize_with(walk.next_id.load(Ordering::Relaxed), || None);
+            for worker in &mut workers {
+                for (id, folder) in worker.folders.drain(..) {
+                    folders[id] = Some(folder);
+                }
+                outside.append(&mut worker.outside);
+            }
+            // The symlink that sorts first is followed next.
+            outside.sort_by(|a: &OutsideLink, b| b.path.cmp(&a.path));
+            let Some(link) = outside.pop() else {
+                break;
+            };
+            let worker = &mut workers[0];
+            self.follow_link(link, &walk, &mut folders, &mut largest, &mut groups, worker);
+        }
+
+        let mut linked = vec![];
+        for worker in workers {
+            largest.merge(worker.largest);
+            self.skipped_links.add(&worker.skipped_links);
+            self.errors.extend(worker.errors);
+            self.files.extend(worker.files);
+            groups.merge(worker.groups);
+            linked.extend(worker.linked);
+        }
+        // Of all the paths to a file, it's counted under the one that sorts first.
+        linked.sort_by(|a, b| a.id.cmp(&b.id).then_with(|| a.file.path.cmp(&b.file.path)));
+        for mut link in linked {
+            if !self.count_once(link.id) {
+                link.file.data.size = self.skipped_links.skip(link.file.data.size);
+                link.file.counted = false;
+            }
+            let folder = folders[link.folder]
+                .as_mut()
+                .expect("files are found in folders that were read");
+            let entry = &mut folder.entry;
+            self.add_file(entry, link.depth, link.file, dir, &mut largest, &mut groups);
+        }
+        self.groups.merge(groups);
+        let root = assemble_tree(folders);
+
+        Ok(Listing {
+            root: dir.to_owned(),
+            kind: EntryKind::Directory,
+            icon: " ".to_owned(),
+            entries: root.children,
+            largest: largest.into_entries(),
+            total: PathData {
+                newest: root.newest,
+                ..total_row(root.size, root.files, root.dirs)
+            },
+        })
+    }
+
+    /// Find the files with identical contents among all the ones scanned so far.
+    pub fn duplicates(&mut self) -> Vec<DuplicateSet> {
+        let files = mem::take(&mut self.files);
+        dupes::find(files, self.size_kind, self.threads, &mut self.errors)
+    }
+
+    /// What the files scanned so far add up to with --by-ext.
+    pub fn groups(&mut self) -> Vec<Group> {
+        mem::take(&mut self.groups).into_groups()
+    }
+
+    /// The group a file is added to, which might mean reading the start of it.
+    fn group_key(&self, group_by: GroupBy, path: &Path, metadata: &Metadata) -> io::Result<String> {
+        let (owner, group) = ownership(metadata);
+        Ok(match group_by {
+            GroupBy::Extension => groups::extension_key(path.as_os_str()),
+            GroupBy::ContentType => magic::sniff(path)?.to_owned(),
+            GroupBy::Owner => owner.map_or(UNKNOWN_OWNER.to_owned(), |uid| self.names.user(uid)),
+            GroupBy::Group => group.map_or(UNKNOWN_OWNER.to_owned(), |gid| self.names.group(gid)),
+        })
+    }
+
+    /// Read everything in the queue with a pool of workers.
+    fn run(&self, walk: &Walk, workers: &mut [Worker]) {
+        let (first, others) = workers.split_at_mut(1);
+        thread::scope(|s| {
+            for worker in others {
+                s.spawn(move || self.work(walk, worker));
+            }
+            self.work(walk, &mut first[0]);
+        });
+    }
+
+    /// Read folders until there are none left.
+    fn work(&self, walk: &Walk, worker: &mut Worker) {
+        while let Some(job) = walk.queue.pop(worker.id) {
+            let entries = job.path.read_dir();
+            self.get_size_of_directory(job, entries, walk, worker);
+            walk.queue.finish();
+        }
+    }
+
+    /// Add up the files directly inside a folder, keeping the ones that are no deeper than
+    /// max_depth, and queue up the folders inside it.
+    fn get_size_of_directory(
+        &self,
+        job: DirJob,
+        entries: io::Result<ReadDir>,
+        walk: &Walk,
+        worker: &mut Worker,
+    ) {
+        let DirJob {
+            path,
+            id,
+            parent,
+            depth,
+            mut entry,
+            ignore,
+        } = job;
+
+        match entries {
+            Ok(entries) => {
+                let mut files = vec![];
+                for file in entries {
+                    match file {
+                        Ok(file) => files.push(file),
+                        Err(e) => worker.errors.push(ScanError::new(&path, e)),
+                    }
+                }
+                let ignore = self.read_ignore_files(&path, &files, ignore, worker);
+
+                for file in files {
+                    // Checked before anything else, so that nothing in an excluded folder is read.
+                    let is_dir = file.file_type().is_ok_and(|t| t.is_dir());
+                    if self.is_excluded(&walk.root, ignore.as_deref(), &file.path(), is_dir) {
+                        continue;
+                    }
+                    let mut metadata = match file.metadata() {
+                        Ok(metadata) => metadata,
+                        Err(e) => {
+                            worker.errors.push(ScanError::new(&file.path(), e));
+                            continue;
+                        }
+                    };
+                    let mut followed = false;
+                    if metadata.is_symlink() && self.follow == Follow::Always {
+                        // A broken symlink is counted as itself, and so is one to a folder inside
+                        // the root, which is counted under its real path.
+                        match fs::metadata(file.path()) {
+                            Ok(target) if !target.is_dir() => {
+                                metadata = target;
+                                followed = true;
+                            }
+                            Ok(target) if !walk.contains(&file.path()) => {
+                                worker.outside.push(OutsideLink {
+                                    path: file.path(),
+                                    name: file.file_name(),
+                                    folder: id,
+                                    depth,
+                                    link: metadata,
+                                    target,
+                                    ignore: ignore.clone(),
+                                });
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    // A folder outside the root that a symlink led to can lead back into it, or
+                    // to another folder that was already read, which is then shown without its
+                    // contents.
+                    let skip_folder = metadata.is_dir() && !self.first_visit(&metadata);
+                    // Checked before counting, so that a file left out doesn't stop another hard
+                    // link to it from being counted.
+                    if !metadata.is_dir() && !self.age.matches(&metadata) {
+                        continue;
+                    }
+                    // Folders only count towards the disk usage, see the note at the top of main.rs
+                    let charge = self.charge(&metadata, followed);
+                    let size = match charge {
+                        // A folder is neither old nor new data, only the files in it are.
+                        _ if metadata.is_dir() && self.age.is_active() => Size::default(),
+                        Charge::Skip => worker.skipped_links.skip(Size::of(&metadata)),
+                        Charge::Now | Charge::Later(_) => Size::of(&metadata),
+                    };
+                    let data = self.path_data(file.file_name(), &metadata, size);
+                    let kind = data.kind;
+                    if metadata.is_dir()
+                        && walk.device.is_some_and(|d| Some(d) != device_id(&metadata))
+                    {
+                        // Nothing on the other filesystem is counted, not even the folder itself.
+                        let entry = PathData {
+                            mount_point: true,
+                            ..data
+                        };
+                        self.add_unread_folder(id, depth, entry, walk, worker);
+                    } else if skip_folder {
+                        self.add_unread_folder(id, depth, data, walk, worker);
+                    } else if metadata.is_dir() {
+                        let job = DirJob {
+                            path: file.path(),
+                            id: walk.next_id.fetch_add(1, Ordering::Relaxed),
+                            parent: id,
+                            depth: depth + 1,
+                            entry: data,
+                            ignore: ignore.clone(),
+                        };
+                        walk.queue.push(worker.id, job);
+                    } else {
+                        if self.collect_files && kind == EntryKind::File {
+                            worker.files.push(file_info(file.path(), &metadata));
+                        }
+                        let path = file.path();
+                        let group = match self.group_by.filter(|_| kind == EntryKind::File) {
+                            Some(group_by) => match self.group_key(group_by, &path, &metadata) {
+                                Ok(key) => Some(key),
+                                Err(e) => {
+                                    worker.errors.push(ScanError::new(&path, e));
+                                    None
+                                }
+                            },
+                            None => None,
+                        };
+                        let file = FileEntry {
+                            path,
+                            data,
+                            group,
+                            counted: !matches!(charge, Charge::Skip),
+                        };
+                        match charge {
+                            Charge::Later(link_id) => worker.linked.push(LinkedFile {
+                                id: link_id,
+                                folder: id,
+                                depth,
+                                file,
+                            }),
+                            Charge::Now | Charge::Skip => {
+                                let (largest, groups) = (&mut worker.largest, &mut worker.groups);
+                                self.add_file(&mut entry, depth, file, &walk.root, largest, groups);
+                            }
+                        }
+                    }
+                }
+            }
+            Err(e) => worker.errors.push(ScanError::new(&path, e)),
+        }
+
+        let folder = Folder {
+            parent,
+            keep: depth <= self.max_depth,
+            entry,
+        };
+        worker.folders.push((id, folder));
+    }
+
+    /// Follow a symlink to a folder outside the root, unless the folder was already read through
+    /// another path, in which case only the symlink itself is counted.
+    fn follow_link(
+        &mut self,
+        link: OutsideLink,
+        walk: &Walk,
+        folders: &mut [Option<Folder>],
+        largest: &mut TopFiles,
+        groups: &mut Groups,
+        worker: &mut Worker,
+    ) {
+        let OutsideLink {
+            path,
+            name,
+            folder,
+            depth,
+            link,
+            target,
+            ignore,
+        } = link;
+        if walk.device.is_some_and(|d| Some(d) != device_id(&target)) {
+            // Nothing on the other filesystem is counted, not even the folder itself.
+            let entry = PathData {
+                mount_point: true,
+                ..self.path_data(name, &target, Size::default())
+            };
+            self.add_unread_folder(folder, depth, entry, walk, worker);
+            return;
+        }
+        if self.first_visit(&target) {
+            let size = if self.age.is_active() {
+                Size::default()
+            } else {
+                Size::of(&target)
+            };
+            let job = DirJob {
+                path,
+                id: walk.next_id.fetch_add(1, Ordering::Relaxed),
+                parent: folder,
+                depth: depth + 1,
+                entry: self.path_data(name, &target, size),
+                ignore,
+            };
+            walk.queue.push(worker.id, job);
+            return;
+        }
+
+        if !self.age.matches(&link) {
+            return;
+        }
+        let counted = match self.charge(&link, false) {
+            Charge::Now => true,
+            Charge::Skip => false,
+            Charge::Later(id) => self.count_once(id),
+        };
+        let size = if counted {
+            Size::of(&link)
+        } else {
+            self.skipped_links.skip(Size::of(&link))
+        };
+        let file = FileEntry {
+            data: self.path_data(name, &link, size),
+            path,
+            group: None,
+            counted,
+        };
+        let folder = folders[folder]
+            .as_mut()
+            .expect("symlinks are found in folders that were read");
+        self.add_file(&mut folder.entry, depth, file, &walk.root, largest, groups);
+    }
+
+    /// An entry for something found in a folder, without anything that might be inside it.
+    fn path_data(&self, name: OsString, metadata: &Metadata, size: Size) -> PathData {
+        let kind = entry_kind(metadata);
+        let executable = is_executable(metadata);
+        let modified = metadata.modified().ok();
+        let (owner, group) = ownership(metadata);
+        PathData {
+            icon: icons::icon(&name, kind, executable, self.nerd_fonts),
+            executable,
+            modified,
+            owner,
+            group,
+            newest: if metadata.is_dir() { None } else { modified },
+            ..PathData::new(name, kind, size)
+        }
+    }
+
+    /// Add a file to the folder it's in at `depth`, and to the largest files and the groups.
+    fn add_file(
+        &self,
+        folder: &mut PathData,
+        depth: usize,
+        file: FileEntry,
+        root: &Path,
+        largest: &mut TopFiles,
+        groups: &mut Groups,
+    ) {
+        let FileEntry {
+            path,
+            data,
+            group,
+            counted,
+        } = file;
+        let rank = data.size.get(self.size_kind);
+        // A file counted elsewhere would only show up here as taking no space.
+        if data.kind == EntryKind::File && counted && largest.wants(rank) {
+            // Named by its path, since it's shown away from its folder.
+            let name = path.strip_prefix(root).unwrap_or(&path);
+            let entry = PathData {
+                icon: data.icon.clone(),
+                executable: data.executable,
+                modified: data.modified,
+                newest: data.newest,
+                owner: data.owner,
+                group: data.group,
+                ..PathData::new(name.as_os_str().to_owned(), data.kind, data.size)
+            };
+            largest.push(rank, entry);
+        }
+        if let Some(key) = group {
+            groups.add(&key, data.size, || path);
+        }
+        folder.size.add(data.size);
+        folder.files += data.files;
+        folder.newest = folder.newest.max(data.newest);
+        if depth < self.max_depth {
+            folder.children.push(data);
+        }
+    }
+
+    /// List a folder inside the one with id `parent` without reading it.
+    fn add_unread_folder(
+        &self,
+        parent: usize,
+        depth: usize,
+        entry: PathData,
+        walk: &Walk,
+        worker: &mut Worker,
+    ) {
+        let folder = Folder {
+            parent,
+            keep: depth < self.max_depth,
+            entry: PathData {
+                size: Size::default(),
+                ..entry
+            },
+        };
+        let id = walk.next_id.fetch_add(1, Ordering::Relaxed);
+        worker.folders.push((id, folder));
+    }
+
+    /// Whether a folder is being read for the first time. Only tracked when following symlinks,
+    /// since that's the only way to get to the same folder twice.
+    fn first_visit(&self, metadata: &Metadata) -> bool {
+        if self.follow != Follow::Always {
+            return true;
+        }
+        match inode(metadata) {
+            Some(id) => self.visited.lock().unwrap().insert(id),
+            None => true,
+        }
+    }
+
+    /// Add the patterns from any ignore files among a folder's entries to the ones from its
+    /// parents.
+    fn read_ignore_files(
+        &self,
+        dir: &Path,
+        files: &[DirEntry],
+        parent: Option<Arc<Ignore>>,
+        worker: &mut Worker,
+    ) -> Option<Arc<Ignore>> {
+        let mut patterns = vec![];
+        for name in &self.ignore_files {
+            if !files.iter().any(|f| f.file_name() == *name) {
+                continue;
+            }
+            let path = dir.join(name);
+            match fs::read_to_string(&path) {
+                Ok(text) => patterns.extend(ignore::parse_lines(&text)),
+                Err(e) => worker.errors.push(ScanError::new(&path, e)),
+            }
+        }
+        if patterns.is_empty() {
+            return parent;
+        }
+        Some(Ignore::new(dir.to_owned(), patterns, parent))
+    }
+
+    fn is_excluded(&self, root: &Path, ignore: Option<&Ignore>, path: &Path, is_dir: bool) -> bool {
+        ignore::excluded_by(&self.exclude, root, path, is_dir)
+            || ignore.is_some_and(|i| i.is_excluded(path, is_dir))
+    }
+
+    /// How an entry adds to the total, given whether it was reached through a symlink.
+    fn charge(&self, metadata: &Metadata, followed: bool) -> Charge {
+        if self.count_links {
+            return Charge::Now;
+        }
+        if let Some(id) = hard_link_id(metadata) {
+            return Charge::Later(id);
+        }
+        // Following symlinks can lead to any file more than once, not just hard linked ones, but
+        // a file without other hard links can only be found once by its real path, which beats
+        // any symlink to it.
+        match inode(metadata).filter(|_| self.follow == Follow::Always && !metadata.is_dir()) {
+            Some(id) if followed => Charge::Later(id),
+            Some(id) if !self.seen.lock().unwrap().insert(id) => Charge::Skip,
+            _ => Charge::Now,
+        }
+    }
+
+    /// Whether a file is counted here, which it isn't if it was already counted through another
+    /// path.
+    fn count_once(&mut self, id: (u64, u64)) -> bool {
+        self.seen.get_mut().unwrap().insert(id)
+    }
+}
+
+/// A file for finding duplicates. Its whole size is used even if it's a hard link that isn't
+/// counted, since it's still a file someone might want to delete.
+fn file_info(path: PathBuf, metadata: &Metadata) -> FileInfo {
+    FileInfo {
+        path,
+        size: Size::of(metadata),
+        inode: inode(metadata),
+    }
+}
+
+/// Add every folder into its parent, returning the root with everything added up. Children are
+/// sorted by name so that the result doesn't depend on the order the folders were read in.
+fn assemble_tree(mut folders: Vec<Option<Folder>>) -> PathData {
+    for id in (1..folders.len()).rev() {
+        let mut folder = folders[id].take().expect("every folder is read once");
+        folder.entry.children.sort_by(|a, b| a.name.cmp(&b.name));
+        let parent = folders[folder.parent]
+            .as_mut()
+            .expect("parents are added up after their children");
+        parent.entry.size.add(folder.entry.size);
+        parent.entry.files += folder.entry.files;
+        parent.entry.dirs += folder.entry.dirs + 1;
+        parent.entry.newest = parent.entry.newest.max(folder.entry.newest);
+        if folder.keep {
+            parent.entry.children.push(folder.entry);
+        }
+    }
+
+    let mut root = folders[0].take().expect("the root is always read").entry;
+    root.children.sort_by(|a, b| a.name.cmp(&b.name));
+    root
+}
+
+#[cfg(test)]
+pub fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("pdu-test-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn missing_root_is_an_error() {
+    let mut scanner = Scanner::new(&Options::default());
+    let result = scanner.get_data_from_path(Path::new("does/not/exist"));
+    let e = result.expect_err("scanning a missing path should fail");
+    assert_eq!(e.path, Path::new("does/not/exist"));
+    assert_eq!(e.source.kind(), std::io::ErrorKind::NotFound);
+    assert!(scanner.errors.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn sparse_files_take_less_space_on_disk() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = temp_dir("sparse");
+    let file = std::fs::File::create(dir.join("sparse")).unwrap();
+    file.set_len(10 << 20).unwrap();
+
+    let listing = Scanner::new(&Options::default())
+        .get_data_from_path(&dir)
+        .unwrap();
+    let sparse = &listing.entries[0];
+    assert_eq!(sparse.size.apparent, 10 << 20);
+    assert!(sparse.size.disk < sparse.size.apparent);
+    let blocks = dir.join("sparse").metadata().unwrap().blocks();
+    assert_eq!(sparse.size.disk, blocks * 512);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn folders_only_take_space_on_disk() {
+    use std::os::unix::fs::MetadataExt;
+
+    let dir = temp_dir("folder-size");
+    std::fs::create_dir(dir.join("folder")).unwrap();
+
+    let listing = Scanner::new(&Options::default())
+        .get_data_from_path(&dir)
+        .unwrap();
+    let folder = &listing.entries[0];
+    assert_eq!(folder.size.apparent, 0);
+    assert!(folder.size.disk > 0);
+    let blocks = dir.join("folder").metadata().unwrap().blocks();
+    assert_eq!(folder.size.disk, blocks * 512);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn hard_links_are_counted_once() {
+    let dir = temp_dir("hard-links");
+    std::fs::write(dir.join("original"), [0u8; 1000]).unwrap();
+    std::fs::create_dir(dir.join("links")).unwrap();
+    std::fs::hard_link(dir.join("original"), dir.join("links/copy")).unwrap();
+
+    let mut scanner = Scanner::new(&Options::default());
+    let listing = scanner.get_data_from_path(&dir).unwrap();
+    assert_eq!(listing.total.size.apparent, 1000);
+    assert_eq!(scanner.skipped_links.links, 1);
+    assert_eq!(scanner.skipped_links.size.apparent, 1000);
+
+    let options = Options {
+        count_links: true,
+        ..Options::default()
+    };
+    let mut scanner = Scanner::new(&options);
+    let listing = scanner.get_data_from_path(&dir).unwrap();
+    assert_eq!(listing.total.size.apparent, 2000);
+    assert_eq!(scanner.skipped_links.links, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn max_depth_keeps_nested_entries() {
+    let dir = temp_dir("max-depth");
+    std::fs::create_dir_all(dir.join("a/b/c")).unwrap();
+    std::fs::write(dir.join("a/b/c/file"), [0u8; 10]).unwrap();
+
+    let options = Options {
+        max_depth: Some(2),
+        ..Options::default()
+    };
+    let mut scanner = Scanner::new(&options);
+    let listing = scanner.get_data_from_path(&dir).unwrap();
+    let a = &listing.entries[0];
+    assert_eq!(a.name, "a");
+    assert_eq!(a.size.apparent, 10);
+    assert_eq!(a.children.len(), 1);
+    assert_eq!((a.files, a.dirs), (1, 2));
+    assert_eq!(listing.total.dirs, 3);
+    let b = &a.children[0];
+    assert_eq!(b.name, "b");
+    assert_eq!(b.size.apparent, 10);
+    assert!(b.children.is_empty());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn threads_do_not_change_the_results() {
+    let dir = temp_dir("threads");
+    for i in 0..20 {
+        let sub = dir.join(format!("{}/{}/{}", i % 3, i % 5, i));
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(sub.join("file"), vec![0u8; i * 100]).unwrap();
+    }
+    for i in 0..20 {
+        // Another hard link to each file in a sibling folder, which sorts before or after it.
+        let file = dir.join(format!("{}/{}/{}/file", i % 3, i % 5, i));
+        std::fs::hard_link(file, dir.join(format!("{}/link{}", (i + 1) % 3, i))).unwrap();
+    }
+
+    let scan = |threads| {
+        let options = Options {
+            max_depth: Some(3),
+            top: Some(5),
+            threads: Some(threads),
+            ..Options::default()
+        };
+        let listing = Scanner::new(&options).get_data_from_path(&dir).unwrap();
+        format!("{:?}", listing)
+    };
+    let sequential = scan(1);
+    for threads in [2, 8] {
+        assert_eq!(scan(threads), sequential);
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn excluded_entries_are_skipped() {
+    let dir = temp_dir("exclude");
+    std::fs::create_dir_all(dir.join("keep/build")).unwrap();
+    std::fs::create_dir_all(dir.join("skip")).unwrap();
+    std::fs::write(dir.join("keep/a.log"), [0u8; 10]).unwrap();
+    std::fs::write(dir.join("keep/b.txt"), [0u8; 20]).unwrap();
+    std::fs::write(dir.join("keep/build/c.txt"), [0u8; 40]).unwrap();
+    std::fs::write(dir.join("skip/d.txt"), [0u8; 80]).unwrap();
+    std::fs::write(dir.join("keep/.pduignore"), "build/\n").unwrap();
+    std::fs::write(dir.join(".gitignore"), "*.log\n").unwrap();
+
+    let options = Options {
+        exclude: vec!["skip".to_owned()],
+        ..Options::default()
+    };
+    let listing = Scanner::new(&options).get_data_from_path(&dir).unwrap();
+    // a.log, b.txt, the .pduignore and the .gitignore.
+    assert_eq!(listing.total.size.apparent, 10 + 20 + 7 + 6);
+    assert_eq!(listing.total.files, 4);
+
+    let options = Options {
+        gitignore: true,
+        ..options
+    };
+    let listing = Scanner::new(&options).get_data_from_path(&dir).unwrap();
+    assert_eq!(listing.total.size.apparent, 20 + 7 + 6);
+    assert!(listing.entries.iter().all(|e| e.name != "skip"));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_followed_without_looping() {
+    let dir = temp_dir("follow");
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::write(dir.join("a/file"), [0u8; 100]).unwrap();
+    std::os::unix::fs::symlink("..", dir.join("a/up")).unwrap();
+    std::os::unix::fs::symlink("a/file", dir.join("link")).unwrap();
+
+    let scan = |follow| {
+        let options = Options {
+            follow,
+            max_depth: Some(2),
+            ..Options::default()
+        };
+        Scanner::new(&options).get_data_from_path(&dir).unwrap()
+    };
+
+    let listing = scan(Follow::Never);
+    let link = listing.entries.iter().find(|e| e.name == "link").unwrap();
+    assert_eq!(link.kind, EntryKind::Symlink);
+    // The symlinks count for the length of their targets.
+    assert_eq!(listing.total.size.apparent, 100 + 2 + 6);
+    assert_eq!(listing.total.files, 1);
+
+    // The file is only counted once, and `up` leads back into the root, so it isn't followed.
+    let listing = scan(Follow::Always);
+    let link = listing.entries.iter().find(|e| e.name == "link").unwrap();
+    assert_eq!(link.kind, EntryKind::File);
+    let a = listing.entries.iter().find(|e| e.name == "a").unwrap();
+    let up = a.children.iter().find(|e| e.name == "up").unwrap();
+    assert_eq!(up.kind, EntryKind::Symlink);
+    assert_eq!(listing.total.size.apparent, 100 + 2);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinked_folders_are_counted_under_one_path() {
+    use std::os::unix::fs::symlink;
+
+    let dir = temp_dir("follow-folders");
+    let root = dir.join("root");
+    for folder in ["root/a", "root/z", "outside"] {
+        std::fs::create_dir_all(dir.join(folder)).unwrap();
+        std::fs::write(dir.join(folder).join("file"), [0u8; 100]).unwrap();
+    }
+    // One symlink that sorts after the folder it points to and one that sorts before it, which
+    // both lose to the real path.
+    symlink("a", root.join("lnk")).unwrap();
+    symlink("z", root.join("b")).unwrap();
+    // Of two symlinks to a folder outside the root, the one that sorts first wins.
+    symlink("../outside", root.join("out2")).unwrap();
+    symlink("../outside", root.join("out1")).unwrap();
+
+    for threads in [1, 8] {
+        let options = Options {
+            follow: Follow::Always,
+            threads: Some(threads),
+            ..Options::default()
+        };
+        let listing = Scanner::new(&options).get_data_from_path(&root).unwrap();
+        let entries: Vec<(&str, EntryKind, u64)> = listing
+            .entries
+            .iter()
+            .map(|e| (e.name.to_str().unwrap(), e.kind, e.size.apparent))
+            .collect();
+        assert_eq!(
+            entries,
+            [
+                ("a", EntryKind::Directory, 100),
+                ("b", EntryKind::Symlink, 1),
+                ("lnk", EntryKind::Symlink, 1),
+                ("out1", EntryKind::Directory, 100),
+                ("out2", EntryKind::Symlink, 10),
+                ("z", EntryKind::Directory, 100),
+            ]
+        );
+    }
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn mount_points_are_listed_without_their_contents() {
+    let dir = temp_dir("mount-point");
+    std::fs::create_dir_all(dir.join("mnt/deeper")).unwrap();
+    std::fs::write(dir.join("mnt/file"), [0u8; 100]).unwrap();
+    std::fs::write(dir.join("file"), [0u8; 10]).unwrap();
+
+    // As if everything in the root were on another filesystem.
+    let metadata = dir.metadata().unwrap();
+    let device = device_id(&metadata).map(|d| d.wrapping_add(1));
+    let mut scanner = Scanner::new(&Options::default());
+    let listing = scanner.walk_directory(&dir, &metadata, device).unwrap();
+    let mnt = listing.entries.iter().find(|e| e.name == "mnt").unwrap();
+    assert!(mnt.mount_point);
+    assert_eq!(mnt.display_name(), "mnt [mount point]");
+    assert_eq!(mnt.size, Size::default());
+    assert_eq!((mnt.files, mnt.dirs), (0, 0));
+    assert!(mnt.children.is_empty());
+    // The mount point itself is still a folder in the root.
+    assert_eq!(listing.total.size.apparent, 10);
+    assert_eq!((listing.total.files, listing.total.dirs), (1, 1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn special_files_are_listed() {
+    let dir = temp_dir("special");
+    let _socket = std::os::unix::net::UnixListener::bind(dir.join("socket")).unwrap();
+
+    let listing = Scanner::new(&Options::default())
+        .get_data_from_path(&dir)
+        .unwrap();
+    assert_eq!(listing.entries.len(), 1);
+    assert_eq!(listing.entries[0].kind, EntryKind::Socket);
+    assert_eq!(listing.total.files, 0);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn largest_files_are_found_at_any_depth() {
+    let dir = temp_dir("top");
+    std::fs::create_dir_all(dir.join("a/b")).unwrap();
+    std::fs::write(dir.join("small"), [0u8; 10]).unwrap();
+    std::fs::write(dir.join("a/medium"), [0u8; 20]).unwrap();
+    std::fs::write(dir.join("a/b/large"), [0u8; 30]).unwrap();
+    // Another link to the large file, which isn't one of the largest even though there's room.
+    std::fs::hard_link(dir.join("a/b/large"), dir.join("link")).unwrap();
+
+    let options = Options {
+        top: Some(4),
+        size_kind: SizeKind::Apparent,
+        threads: Some(2),
+        ..Options::default()
+    };
+    let listing = Scanner::new(&options).get_data_from_path(&dir).unwrap();
+    let names: Vec<_> = listing.largest.iter().map(|e| e.name.clone()).collect();
+    assert_eq!(
+        names,
+        [
+            Path::new("a/b/large"),
+            Path::new("a/medium"),
+            Path::new("small")
+        ]
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn only_files_of_the_right_age_are_counted() {
+    use std::time::Duration;
+
+    let dir = temp_dir("age");
+    std::fs::create_dir_all(dir.join("a")).unwrap();
+    std::fs::write(dir.join("a/new"), [0u8; 10]).unwrap();
+    std::fs::write(dir.join("a/old"), [0u8; 20]).unwrap();
+    let old = SystemTime::now() - Duration::from_secs(100 * 24 * 60 * 60);
+    let file = std::fs::File::options()
+        .write(true)
+        .open(dir.join("a/old"))
+        .unwrap();
+    file.set_modified(old).unwrap();
+
+    let scan = |older_than, newer_than| {
+        let options = Options {
+            older_than,
+            newer_than,
+            size_kind: SizeKind::Apparent,
+            ..Options::default()
+        };
+        let listing = Scanner::new(&options).get_data_from_path(&dir).unwrap();
+        (listing.total.size.apparent, listing.total.files)
+    };
+    let quarter = Some(Duration::from_secs(90 * 24 * 60 * 60));
+    assert_eq!(scan(None, None), (30, 2));
+    assert_eq!(scan(quarter, None), (20, 1));
+    assert_eq!(scan(None, quarter), (10, 1));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,207 @@
+/**
+* Formatting sizes for people to read.
+*
+* By default sizes are shown in binary units (KiB, MiB, ... in steps of 1024) with three decimals.
+* --si uses decimal units (kB, MB, ... in steps of 1000) instead, --precision changes the number of
+* decimals, --bytes shows the exact number of bytes, and --block-size shows the number of blocks
+* of a given size, rounded up like `du` does.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SizeFormat {
+    pub units: Units,
+    /// Decimals to show for sizes in binary or SI units.
+    pub precision: usize,
+}
+
+impl Default for SizeFormat {
+    fn default() -> Self {
+        SizeFormat {
+            units: Units::default(),
+            precision: 3,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Units {
+    /// KiB, MiB, GiB, ...
+    #[default]
+    Binary,
+    /// kB, MB, GB, ...
+    Si,
+    /// The exact number of bytes, with thousands separators.
+    Bytes,
+    /// The number of blocks of this many bytes, rounded up.
+    Blocks(u64),
+}
+
+const BINARY_UNITS: [&str; 9] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB", "ZiB", "YiB"];
+const SI_UNITS: [&str; 9] = ["B", "kB", "MB", "GB", "TB", "PB", "EB", "ZB", "YB"];
+
+impl SizeFormat {
+    pub fn format(&self, bytes: u64) -> String {
+        match self.units {
+            Units::Binary => scaled(bytes, 1024.0, &BINARY_UNITS, self.precision),
+            Units::Si => scaled(bytes, 1000.0, &SI_UNITS, self.precision),
+            Units::Bytes => group_thousands(bytes),
+            Units::Blocks(block_size) => bytes.div_ceil(block_size).to_string(),
+        }
+    }
+}
+
+fn scaled(bytes: u64, step: f64, units: &[&str], precision: usize) -> String {
+    let mut out = bytes as f64;
+    let mut unit = units.len() - 1;
+    for (i, _) in units.iter().enumerate() {
+        if out < step {
+            unit = i;
+            break;
+        }
+        out /= step;
+    }
+    format!("{:.*} {}", precision, out, units[unit])
+}
+
+/// Write a number with a comma between every group of three digits, like 1,234,567.
+pub fn group_thousands(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() * 4 / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Parse a block size the way `du --block-size` does, see `parse_size`.
+pub fn parse_block_size(value: &str) -> Result<u64, String> {
+    match parse_with_unit(value, "block size")? {
+        0 => Err("block size must be at least 1".to_owned()),
+        size => Ok(size),
+    }
+}
+
+/// Parse a size the way `du` does: a number, a unit, or a number followed by a unit. K, M, G, ...
+/// (optionally followed by iB) are powers of 1024, and KB, MB, GB, ... are powers of 1000.
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    parse_with_unit(value, "size")
+}
+
+fn parse_with_unit(value: &str, what: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid {} '{}'", what, value);
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number = if number.is_empty() {
+        1
+    } else {
+        number.parse::<u64>().map_err(|_| invalid())?
+    };
+
+    let (prefix, step) = if let Some(prefix) = unit.strip_suffix("iB") {
+        (prefix, 1024)
+    } else if let Some(prefix) = unit.strip_suffix('B') {
+        (prefix, 1000)
+    } else {
+        (unit, 1024)
+    };
+    let power = match prefix.to_ascii_uppercase().as_str() {
+        "" if unit.is_empty() => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        "E" => 6,
+        _ => return Err(invalid()),
+    };
+    (step as u64)
+        .checked_pow(power)
+        .and_then(|m| m.checked_mul(number))
+        .ok_or_else(|| format!("{} '{}' is too large", what, value))
+}
+
+#[test]
+fn binary_units() {
+    let format = SizeFormat::default();
+    assert_eq!(format.format(0), "0.000 B");
+    assert_eq!(format.format(1023), "1023.000 B");
+    assert_eq!(format.format(1536), "1.500 KiB");
+    assert_eq!(format.format(5 << 30), "5.000 GiB");
+    assert_eq!(format.format(u64::MAX), "16.000 EiB");
+}
+
+#[test]
+fn si_units_and_precision() {
+    let si = SizeFormat {
+        units: Units::Si,
+        precision: 1,
+    };
+    assert_eq!(si.format(999), "999.0 B");
+    assert_eq!(si.format(1000), "1.0 kB");
+    assert_eq!(si.format(1_250_000), "1.2 MB");
+    assert_eq!(si.format(3_000_000_000), "3.0 GB");
+
+    let whole = SizeFormat {
+        precision: 0,
+        ..SizeFormat::default()
+    };
+    assert_eq!(whole.format(1024), "1 KiB");
+    assert_eq!(whole.format(2047), "2 KiB");
+}
+
+#[test]
+fn exact_bytes() {
+    let bytes = SizeFormat {
+        units: Units::Bytes,
+        ..SizeFormat::default()
+    };
+    assert_eq!(bytes.format(0), "0");
+    assert_eq!(bytes.format(999), "999");
+    assert_eq!(bytes.format(1000), "1,000");
+    assert_eq!(bytes.format(1_234_567), "1,234,567");
+    assert_eq!(bytes.format(u64::MAX), "18,446,744,073,709,551,615");
+}
+
+#[test]
+fn blocks_are_rounded_up() {
+    let blocks = SizeFormat {
+        units: Units::Blocks(1024),
+        ..SizeFormat::default()
+    };
+    assert_eq!(blocks.format(0), "0");
+    assert_eq!(blocks.format(1), "1");
+    assert_eq!(blocks.format(1024), "1");
+    assert_eq!(blocks.format(1025), "2");
+}
+
+#[test]
+fn block_sizes() {
+    assert_eq!(parse_block_size("512"), Ok(512));
+    assert_eq!(parse_block_size("K"), Ok(1024));
+    assert_eq!(parse_block_size("4k"), Ok(4096));
+    assert_eq!(parse_block_size("KiB"), Ok(1024));
+    assert_eq!(parse_block_size("KB"), Ok(1000));
+    assert_eq!(parse_block_size("2MB"), Ok(2_000_000));
+    assert_eq!(parse_block_size("1G"), Ok(1 << 30));
+    assert!(parse_block_size("0").is_err());
+    assert!(parse_block_size("").is_err());
+    assert!(parse_block_size("12X").is_err());
+    assert!(parse_block_size("B").is_err());
+    assert!(parse_block_size("99999E").is_err());
+}
+
+#[test]
+fn sizes() {
+    assert_eq!(parse_size("0"), Ok(0));
+    assert_eq!(parse_size("10MiB"), Ok(10 << 20));
+    assert_eq!(parse_size("10M"), Ok(10 << 20));
+    assert_eq!(parse_size("10MB"), Ok(10_000_000));
+    assert!(parse_size("10 MiB").is_err());
+}
@@ -0,0 +1,139 @@
+/**
+* The order entries are listed in (--sort and --reverse).
+*
+* Every order falls back on the name and then on the raw bytes of the name, so that entries that
+* are equal by the sort key always come out in the same order.
+*/
+use std::{cmp::Ordering, ffi::OsStr, path::Path};
+
+use crate::{
+    args::{Options, SortKey},
+    PathData,
+};
+
+pub fn sort(entries: &mut [PathData], options: &Options) {
+    entries.sort_by(|a, b| {
+        let order = compare(a, b, options);
+        if options.reverse {
+            order.reverse()
+        } else {
+            order
+        }
+    });
+}
+
+/// Whether the Total row goes above the entries rather than below them. It's sorted like any
+/// other row when sorting by size or counts, since then it's the biggest, unless it's pinned.
+pub fn total_first(options: &Options) -> bool {
+    options.reverse
+        && !options.pin_total
+        && matches!(options.sort, SortKey::Size | SortKey::Count | SortKey::Dirs)
+}
+
+fn compare(a: &PathData, b: &PathData, options: &Options) -> Ordering {
+    let kind = options.size_kind;
+    let order = match options.sort {
+        SortKey::Size => a.size.get(kind).cmp(&b.size.get(kind)),
+        SortKey::Name => Ordering::Equal,
+        SortKey::Mtime => a.modified.cmp(&b.modified),
+        SortKey::Count => a.files.cmp(&b.files),
+        SortKey::Dirs => a.dirs.cmp(&b.dirs),
+        SortKey::Ext => extension(&a.name).cmp(&extension(&b.name)),
+    };
+    order.then_with(|| compare_names(&a.name, &b.name))
+}
+
+/// Natural order, then plain byte order for names that only differ in case or leading zeros.
+pub fn compare_names(a: &OsStr, b: &OsStr) -> Ordering {
+    natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()).then_with(|| a.cmp(b))
+}
+
+pub fn extension(name: &OsStr) -> String {
+    Path::new(name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Compare names the way people read them, ignoring case and comparing runs of digits by their
+/// value, so that `file2` comes before `file10` and `v1.9` before `v1.10`.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    loop {
+        let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+            return a.len().cmp(&b.len());
+        };
+        let order = if x.is_ascii_digit() && y.is_ascii_digit() {
+            let (x, rest_a) = split_digits(a);
+            let (y, rest_b) = split_digits(b);
+            a = rest_a;
+            b = rest_b;
+            // Without leading zeros, a longer number is a bigger one.
+            let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+            x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+        } else {
+            a = &a[x.len_utf8()..];
+            b = &b[y.len_utf8()..];
+            x.to_lowercase().cmp(y.to_lowercase())
+        };
+        if order != Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+fn split_digits(s: &str) -> (&str, &str) {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s.split_at(end)
+}
+
+#[test]
+fn natural_order() {
+    let mut names = vec![
+        "file10", "File2", "file1", "v1.10", "v1.9", "a", "B", "file01",
+    ];
+    names.sort_by(|a, b| compare_names(OsStr::new(a), OsStr::new(b)));
+    assert_eq!(
+        names,
+        ["a", "B", "file01", "file1", "File2", "file10", "v1.9", "v1.10"]
+    );
+}
+
+#[test]
+fn ties_are_broken_by_name() {
+    use crate::{scan::Size, EntryKind};
+    use std::ffi::OsString;
+
+    let entry = |name: &str, apparent, ext: &str| {
+        let name = OsString::from(format!("{}{}", name, ext));
+        PathData::new(name, EntryKind::File, Size { apparent, disk: 0 })
+    };
+    let mut entries = vec![
+        entry("b", 5, ".txt"),
+        entry("a", 5, ".rs"),
+        entry("c", 1, ".txt"),
+    ];
+    let names = |entries: &[PathData]| -> Vec<String> {
+        entries
+            .iter()
+            .map(|e| e.name.to_string_lossy().into_owned())
+            .collect()
+    };
+
+    let mut options = Options {
+        size_kind: crate::args::SizeKind::Apparent,
+        ..Options::default()
+    };
+    sort(&mut entries, &options);
+    assert_eq!(names(&entries), ["c.txt", "a.rs", "b.txt"]);
+
+    options.reverse = true;
+    sort(&mut entries, &options);
+    assert_eq!(names(&entries), ["b.txt", "a.rs", "c.txt"]);
+    assert!(total_first(&options));
+
+    options.sort = SortKey::Ext;
+    options.reverse = false;
+    sort(&mut entries, &options);
+    assert_eq!(names(&entries), ["a.rs", "b.txt", "c.txt"]);
+}
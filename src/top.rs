@@ -0,0 +1,112 @@
+/**
+* Keeping track of the largest files (--top).
+*
+* Every worker keeps its own N largest files in a min-heap, so that deciding whether a file makes
+* the cut only means comparing it to the smallest one kept, and memory use doesn't depend on how
+* many files there are. The workers' heaps are merged once the walk is done.
+*/
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+use crate::PathData;
+
+/// A file that might be one of the largest, ranked by its size and then by its name so that
+/// which files are kept never depends on the order they were found in.
+#[derive(Debug)]
+struct Candidate {
+    rank: u64,
+    entry: PathData,
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // For the same size, the name that sorts first wins.
+        self.rank
+            .cmp(&other.rank)
+            .then_with(|| other.entry.name.cmp(&self.entry.name))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+#[derive(Debug, Default)]
+pub struct TopFiles {
+    limit: usize,
+    /// The smallest of the files kept is on top.
+    heap: BinaryHeap<Reverse<Candidate>>,
+}
+
+impl TopFiles {
+    pub fn new(limit: usize) -> Self {
+        TopFiles {
+            limit,
+            heap: BinaryHeap::with_capacity(limit + 1),
+        }
+    }
+
+    /// Whether a file of this size could be kept, so that the caller can skip building the entry
+    /// for the vast majority of files that won't be.
+    pub fn wants(&self, rank: u64) -> bool {
+        self.heap.len() < self.limit || self.heap.peek().is_some_and(|min| rank >= min.0.rank)
+    }
+
+    pub fn push(&mut self, rank: u64, entry: PathData) {
+        if self.limit == 0 {
+            return;
+        }
+        self.heap.push(Reverse(Candidate { rank, entry }));
+        if self.heap.len() > self.limit {
+            self.heap.pop();
+        }
+    }
+
+    pub fn merge(&mut self, other: TopFiles) {
+        for Reverse(candidate) in other.heap {
+            self.push(candidate.rank, candidate.entry);
+        }
+    }
+
+    /// The files kept, largest first.
+    pub fn into_entries(self) -> Vec<PathData> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(candidate)| candidate.entry)
+            .collect()
+    }
+}
+
+#[test]
+fn only_the_largest_are_kept() {
+    use crate::{scan::Size, EntryKind};
+    use std::ffi::OsString;
+
+    let entry = |name: &str| PathData::new(OsString::from(name), EntryKind::File, Size::default());
+    let mut first = TopFiles::new(3);
+    let mut second = TopFiles::new(3);
+    for (i, size) in [5, 1, 9, 3, 7, 9].into_iter().enumerate() {
+        let top = if i % 2 == 0 { &mut first } else { &mut second };
+        if top.wants(size) {
+            top.push(size, entry(&format!("{}-{}", size, i)));
+        }
+    }
+    assert!(!first.wants(4));
+    first.merge(second);
+
+    let names: Vec<_> = first.into_entries().into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["9-2", "9-5", "7-4"]);
+}
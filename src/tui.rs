@@ -0,0 +1,525 @@
+/**
+* The interactive browser (--interactive), a bit like ncdu.
+*
+* The whole tree is scanned once up front and kept in memory, and then browsed one folder at a
+* time. There's no terminal library here, so the terminal is switched into raw mode with `stty`
+* and drawn on with plain ANSI escape codes.
+*/
+use std::{
+    env,
+    fs::File,
+    io::{self, IsTerminal, Read, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use crate::{args::SizeKind, size::SizeFormat, sort, EntryKind, PathData};
+
+const HELP: &str =
+    "↑↓ move  →/enter open  ←/backspace back  s size  n name  r reverse  / search  q quit";
+
+pub fn run(root: PathData, size_kind: SizeKind, size_format: SizeFormat) -> io::Result<()> {
+    let mut terminal = Terminal::open()?;
+    let mut browser = Browser::new(root, size_kind, size_format);
+    loop {
+        let (height, width) = terminal.size();
+        let screen = browser.render(height, width);
+        terminal.tty.write_all(screen.as_bytes())?;
+        terminal.tty.flush()?;
+
+        for key in terminal.read_keys()? {
+            if !browser.handle(key) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Enter,
+    Backspace,
+    Escape,
+    /// Ctrl-C, which quits whatever's going on.
+    Interrupt,
+    Char(char),
+}
+
+/// Turn what the terminal sent us into keys. Escape sequences always arrive in a single read, so
+/// an escape byte on its own really is the escape key.
+fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = vec![];
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' if chars.peek() == Some(&'[') || chars.peek() == Some(&'O') => {
+                chars.next();
+                let mut sequence = String::new();
+                for c in chars.by_ref() {
+                    sequence.push(c);
+                    if c.is_ascii_alphabetic() || c == '~' {
+                        break;
+                    }
+                }
+                match sequence.as_str() {
+                    "A" => Key::Up,
+                    "B" => Key::Down,
+                    "C" => Key::Right,
+                    "D" => Key::Left,
+                    "H" | "1~" | "7~" => Key::Home,
+                    "F" | "4~" | "8~" => Key::End,
+                    "5~" => Key::PageUp,
+                    "6~" => Key::PageDown,
+                    _ => continue,
+                }
+            }
+            '\x1b' => Key::Escape,
+            '\r' | '\n' => Key::Enter,
+            '\x7f' | '\x08' => Key::Backspace,
+            // Ctrl-C doesn't send a signal in raw mode.
+            '\x03' => Key::Interrupt,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
+
+/// The terminal we're drawing on. Dropping it puts the terminal back the way it was.
+struct Terminal {
+    tty: File,
+    /// The settings from before we switched to raw mode, as printed by `stty -g`.
+    saved: String,
+}
+
+impl Terminal {
+    fn open() -> io::Result<Terminal> {
+        let tty = File::options().read(true).write(true).open("/dev/tty")?;
+        let saved = stty(&tty, &["-g"])?;
+        stty(&tty, &["raw", "-echo"])?;
+        let mut terminal = Terminal {
+            tty,
+            saved: saved.trim().to_owned(),
+        };
+        // Switch to the alternate screen and hide the cursor.
+        terminal.tty.write_all(b"\x1b[?1049h\x1b[?25l")?;
+        Ok(terminal)
+    }
+
+    /// The number of rows and columns, re-read every time so that resizing just works.
+    fn size(&self) -> (usize, usize) {
+        let size = stty(&self.tty, &["size"]).unwrap_or_default();
+        let mut numbers = size.split_whitespace().map(|n| n.parse().unwrap_or(0));
+        match (numbers.next(), numbers.next()) {
+            (Some(rows), Some(columns)) if rows > 0 && columns > 0 => (rows, columns),
+            _ => (24, 80),
+        }
+    }
+
+    fn read_keys(&mut self) -> io::Result<Vec<Key>> {
+        let mut buf = [0u8; 64];
+        let n = self.tty.read(&mut buf)?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "terminal closed",
+            ));
+        }
+        Ok(parse_keys(&buf[..n]))
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(b"\x1b[?25h\x1b[?1049l");
+        let _ = self.tty.flush();
+        let _ = stty(&self.tty, &[&self.saved]);
+    }
+}
+
+/// The width of the terminal pdu is writing to, if it's writing to one.
+pub fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    if let Some(columns) = env::var("COLUMNS").ok().and_then(|c| c.parse().ok()) {
+        return Some(columns);
+    }
+    let tty = File::open("/dev/tty").ok()?;
+    let size = stty(&tty, &["size"]).ok()?;
+    size.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn stty(tty: &File, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(tty.try_clone()?))
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("stty {} failed", args.join(" "))));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SortKey {
+    Size,
+    Name,
+}
+
+struct Browser {
+    root: PathData,
+    size_kind: SizeKind,
+    size_format: SizeFormat,
+    /// Indices into `children` leading from the root to the folder being shown.
+    path: Vec<usize>,
+    /// Index into the shown folder's `children` of the selected entry, if any entry is shown.
+    selected: Option<usize>,
+    /// The first row on screen.
+    scroll: usize,
+    sort: SortKey,
+    reverse: bool,
+    /// Only entries containing this (ignoring case) are shown.
+    search: String,
+    /// Whether keys are being typed into the search.
+    searching: bool,
+}
+
+impl Browser {
+    fn new(root: PathData, size_kind: SizeKind, size_format: SizeFormat) -> Self {
+        let mut browser = Browser {
+            root,
+            size_kind,
+            size_format,
+            path: vec![],
+            selected: None,
+            scroll: 0,
+            sort: SortKey::Size,
+            reverse: false,
+            search: String::new(),
+            searching: false,
+        };
+        browser.select_first();
+        browser
+    }
+
+    fn current(&self) -> &PathData {
+        let mut node = &self.root;
+        for &i in &self.path {
+            node = &node.children[i];
+        }
+        node
+    }
+
+    /// The indices of the entries to show, in the order to show them in.
+    fn rows(&self) -> Vec<usize> {
+        let children = &self.current().children;
+        let search = self.search.to_lowercase();
+        let mut rows: Vec<usize> = (0..children.len())
+            .filter(|&i| {
+                search.is_empty()
+                    || children[i]
+                        .name
+                        .to_string_lossy()
+                        .to_lowercase()
+                        .contains(&search)
+            })
+            .collect();
+
+        let kind = self.size_kind;
+        match self.sort {
+            // Biggest first, since that's usually what you're looking for.
+            SortKey::Size => rows.sort_by(|&a, &b| {
+                children[b]
+                    .size
+                    .get(kind)
+                    .cmp(&children[a].size.get(kind))
+                    .then_with(|| sort::compare_names(&children[a].name, &children[b].name))
+            }),
+            SortKey::Name => {
+                rows.sort_by(|&a, &b| sort::compare_names(&children[a].name, &children[b].name))
+            }
+        }
+        if self.reverse {
+            rows.reverse();
+        }
+        rows
+    }
+
+    fn select_first(&mut self) {
+        self.selected = self.rows().first().copied();
+        self.scroll = 0;
+    }
+
+    /// Move the selection by `by` rows, stopping at either end.
+    fn move_selection(&mut self, by: isize) {
+        let rows = self.rows();
+        if rows.is_empty() {
+            return;
+        }
+        let row = rows
+            .iter()
+            .position(|&i| Some(i) == self.selected)
+            .unwrap_or(0);
+        let row = (row as isize + by).clamp(0, rows.len() as isize - 1);
+        self.selected = Some(rows[row as usize]);
+    }
+
+    /// Handle a key press, returning false when it's time to quit.
+    fn handle(&mut self, key: Key) -> bool {
+        if key == Key::Interrupt {
+            return false;
+        }
+        if self.searching {
+            match key {
+                Key::Enter => self.searching = false,
+                Key::Escape => {
+                    self.searching = false;
+                    self.search.clear();
+                }
+                Key::Backspace => {
+                    self.search.pop();
+                }
+                Key::Char(c) if !c.is_control() => self.search.push(c),
+                _ => return true,
+            }
+            self.select_first();
+            return true;
+        }
+
+        match key {
+            Key::Char('q') => return false,
+            Key::Up | Key::Char('k') => self.move_selection(-1),
+            Key::Down | Key::Char('j') => self.move_selection(1),
+            Key::PageUp => self.move_selection(-10),
+            Key::PageDown => self.move_selection(10),
+            Key::Home | Key::Char('g') => self.move_selection(isize::MIN / 2),
+            Key::End | Key::Char('G') => self.move_selection(isize::MAX / 2),
+            Key::Right | Key::Enter | Key::Char('l') => {
+                let children = &self.current().children;
+                let selected = self.selected;
+                if let Some(i) = selected.filter(|&i| children[i].kind == EntryKind::Directory) {
+                    self.path.push(i);
+                    self.search.clear();
+                    self.select_first();
+                }
+            }
+            Key::Left | Key::Backspace | Key::Char('h') => {
+                if let Some(i) = self.path.pop() {
+                    self.search.clear();
+                    self.selected = Some(i);
+                    self.scroll = 0;
+                }
+            }
+            Key::Char('s') => self.sort = SortKey::Size,
+            Key::Char('n') => self.sort = SortKey::Name,
+            Key::Char('r') => self.reverse = !self.reverse,
+            Key::Char('/') => {
+                self.searching = true;
+                self.search.clear();
+            }
+            Key::Escape => {
+                self.search.clear();
+                if self.selected.is_none() {
+                    self.select_first();
+                }
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn title(&self) -> String {
+        let mut path = PathBuf::from(&self.root.name);
+        let mut node = &self.root;
+        for &i in &self.path {
+            node = &node.children[i];
+            path.push(&node.name);
+        }
+        path.display().to_string()
+    }
+
+    fn render(&mut self, height: usize, width: usize) -> String {
+        let rows = self.rows();
+        // Leave room for the title and the status line.
+        let visible = height.saturating_sub(2).max(1);
+        let row = rows
+            .iter()
+            .position(|&i| Some(i) == self.selected)
+            .unwrap_or(0);
+        if row < self.scroll {
+            self.scroll = row;
+        } else if row >= self.scroll + visible {
+            self.scroll = row + 1 - visible;
+        }
+
+        let current = self.current();
+        let mut screen = String::from("\x1b[H\x1b[2J");
+        screen.push_str(&fit(&format!(" pdu: {}", self.title()), width));
+        for &i in rows.iter().skip(self.scroll).take(visible) {
+            let entry = &current.children[i];
+            let line = format!(
+                "{:>13}  {}{}",
+                entry.get_human_readable_size(self.size_kind, self.size_format),
+                entry.display_name(),
+                entry.kind.marker()
+            );
+            screen.push_str("\r\n");
+            if Some(i) == self.selected {
+                screen.push_str(&format!("\x1b[7m{:<width$}\x1b[0m", fit(&line, width)));
+            } else {
+                screen.push_str(&fit(&line, width));
+            }
+        }
+        if rows.is_empty() {
+            screen.push_str("\r\n");
+            screen.push_str(if self.search.is_empty() {
+                "  (empty)"
+            } else {
+                "  (no matches)"
+            });
+        }
+
+        let status = if self.searching {
+            format!(" Search: {}_", self.search)
+        } else {
+            format!(
+                " Total: {}  Files: {}  Folders: {}  |  {}",
+                current.get_human_readable_size(self.size_kind, self.size_format),
+                current.files,
+                current.dirs,
+                HELP
+            )
+        };
+        screen.push_str(&format!("\x1b[{};1H", height));
+        screen.push_str(&fit(&status, width));
+        screen
+    }
+}
+
+/// Cut a line down to the width of the terminal.
+fn fit(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+#[cfg(test)]
+fn sample_tree() -> PathData {
+    use crate::scan::Size;
+    use std::ffi::OsString;
+
+    let file = |name: &str, bytes| {
+        let size = Size {
+            apparent: bytes,
+            disk: bytes,
+        };
+        PathData::new(OsString::from(name), EntryKind::File, size)
+    };
+    let mut small = PathData::new(
+        OsString::from("small"),
+        EntryKind::Directory,
+        Default::default(),
+    );
+    small.children.push(file("a", 1));
+    let mut big = PathData::new(
+        OsString::from("big"),
+        EntryKind::Directory,
+        Default::default(),
+    );
+    big.children.push(file("b", 10));
+    big.children.push(file("c", 20));
+    big.size.add(crate::scan::Size {
+        apparent: 30,
+        disk: 30,
+    });
+    let mut root = PathData::new(
+        OsString::from("root"),
+        EntryKind::Directory,
+        Default::default(),
+    );
+    root.children = vec![small, big, file("d", 5)];
+    root
+}
+
+#[test]
+fn arrow_keys_and_plain_escape() {
+    assert_eq!(
+        parse_keys(b"\x1b[A\x1b[B\x1bOCq\x1b"),
+        vec![Key::Up, Key::Down, Key::Right, Key::Char('q'), Key::Escape]
+    );
+    assert_eq!(
+        parse_keys(b"\x1b[5~\x7f\r"),
+        vec![Key::PageUp, Key::Backspace, Key::Enter]
+    );
+}
+
+#[test]
+fn browsing_into_folders_and_back() {
+    let mut browser = Browser::new(sample_tree(), SizeKind::Apparent, SizeFormat::default());
+    // Biggest first: big, d, small.
+    assert_eq!(browser.rows(), vec![1, 2, 0]);
+    assert_eq!(browser.selected, Some(1));
+
+    browser.handle(Key::Right);
+    assert_eq!(browser.title(), "root/big");
+    assert_eq!(browser.rows(), vec![1, 0]);
+
+    browser.handle(Key::Left);
+    assert_eq!(browser.title(), "root");
+    assert_eq!(browser.selected, Some(1));
+
+    browser.handle(Key::Char('n'));
+    assert_eq!(browser.rows(), vec![1, 2, 0]);
+    browser.handle(Key::Char('r'));
+    assert_eq!(browser.rows(), vec![0, 2, 1]);
+    assert!(!browser.handle(Key::Char('q')));
+}
+
+#[test]
+fn searching_filters_the_folder() {
+    let mut browser = Browser::new(sample_tree(), SizeKind::Apparent, SizeFormat::default());
+    for key in parse_keys(b"/SM") {
+        browser.handle(key);
+    }
+    assert_eq!(browser.rows(), vec![0]);
+    assert_eq!(browser.selected, Some(0));
+    // The search stays in place once it's confirmed, until escape is pressed.
+    browser.handle(Key::Enter);
+    assert_eq!(browser.rows(), vec![0]);
+    browser.handle(Key::Escape);
+    assert_eq!(browser.rows().len(), 3);
+}
+
+#[test]
+fn nothing_is_opened_when_the_search_matches_nothing() {
+    let mut browser = Browser::new(sample_tree(), SizeKind::Apparent, SizeFormat::default());
+    for key in parse_keys(b"/zzz\r") {
+        browser.handle(key);
+    }
+    assert!(browser.rows().is_empty());
+    assert_eq!(browser.selected, None);
+    browser.handle(Key::Right);
+    assert_eq!(browser.title(), "root");
+    browser.handle(Key::Escape);
+    assert_eq!(browser.selected, Some(1));
+}
+
+#[test]
+fn ctrl_c_quits_while_searching() {
+    let mut browser = Browser::new(sample_tree(), SizeKind::Apparent, SizeFormat::default());
+    let keys = parse_keys(b"/q\x03");
+    assert_eq!(keys[2], Key::Interrupt);
+    assert!(browser.handle(keys[0]));
+    assert!(browser.handle(keys[1]));
+    assert!(!browser.handle(keys[2]));
+    assert_eq!(browser.search, "q");
+}
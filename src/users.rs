@@ -0,0 +1,83 @@
+/**
+* The names of users and groups, for --by-owner, --by-group, --owner and --group.
+*
+* Names are read from the local passwd and group databases (/etc/passwd and /etc/group) rather
+* than looked up through NSS, so users and groups that only exist in a directory service like
+* LDAP are shown by their numeric id.
+*/
+use std::{collections::HashMap, fs};
+
+const PASSWD_FILE: &str = "/etc/passwd";
+const GROUP_FILE: &str = "/etc/group";
+
+#[derive(Debug, Default)]
+pub struct Names {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Names {
+    /// Read the databases, leaving out any that can't be read.
+    pub fn load() -> Self {
+        let read = |path| fs::read_to_string(path).map(|text| parse(&text));
+        Names {
+            users: read(PASSWD_FILE).unwrap_or_default(),
+            groups: read(GROUP_FILE).unwrap_or_default(),
+        }
+    }
+
+    pub fn user(&self, uid: u32) -> String {
+        self.users
+            .get(&uid)
+            .cloned()
+            .unwrap_or_else(|| uid.to_string())
+    }
+
+    pub fn group(&self, gid: u32) -> String {
+        self.groups
+            .get(&gid)
+            .cloned()
+            .unwrap_or_else(|| gid.to_string())
+    }
+}
+
+/// Read the ids and names out of a passwd or group file, which both have lines like
+/// `name:password:id:...`. When an id has more than one name, the first one wins, like it does
+/// for `ls`.
+fn parse(text: &str) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    for line in text.lines() {
+        if line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split(':');
+        let (Some(name), Some(_), Some(id)) = (fields.next(), fields.next(), fields.next()) else {
+            continue;
+        };
+        if let Ok(id) = id.parse() {
+            names.entry(id).or_insert_with(|| name.to_owned());
+        }
+    }
+    names
+}
+
+#[test]
+fn databases_are_parsed() {
+    let passwd = concat!(
+        "# comment\n",
+        "root:x:0:0:root:/root:/bin/sh\n",
+        "alice:x:1000:1000::/home/alice:/bin/sh\n",
+        "toor:x:0:0::/root:/bin/sh\n",
+        "broken\n",
+        "bad:x:id:0::/:/bin/sh\n",
+    );
+    let names = Names {
+        users: parse(passwd),
+        groups: parse("wheel:x:10:alice,bob\n"),
+    };
+    assert_eq!(names.user(0), "root");
+    assert_eq!(names.user(1000), "alice");
+    assert_eq!(names.user(4242), "4242");
+    assert_eq!(names.group(10), "wheel");
+    assert_eq!(names.group(0), "0");
+}